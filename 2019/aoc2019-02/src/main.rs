@@ -1,3 +1,5 @@
+use aoc2019::intcode::compile::CompiledProgram;
+use aoc2019::intcode::symbolic::SymbolicVM;
 use std::error::Error;
 use std::io::{self, Read, Write};
 
@@ -13,7 +15,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn part1(input: &str) -> Result<i32, Box<dyn Error>> {
+fn part1(input: &str) -> Result<i64, Box<dyn Error>> {
     let initial_memory = aoc2019::intcode::parse_intcode_program(input)?;
 
     let result_1202 = aoc2019::intcode::perform_computation(12, 2, &initial_memory)?;
@@ -22,12 +24,30 @@ fn part1(input: &str) -> Result<i32, Box<dyn Error>> {
 }
 
 fn discover_noun_and_verb(
-    desired_result: i32,
-    initial_memory: &Vec<i32>,
-) -> Result<(i32, i32), Box<dyn Error>> {
+    desired_result: i64,
+    initial_memory: &[i64],
+) -> Result<(i64, i64), Box<dyn Error>> {
+    // The result is usually linear in noun and verb, which can be solved directly;
+    // otherwise try every pair
+    let mut symbolic = SymbolicVM::new(initial_memory);
+    symbolic.set_symbol(1, "noun");
+    symbolic.set_symbol(2, "verb");
+    let linear = match symbolic.run() {
+        Ok(()) => symbolic.peek(0).linear(),
+        Err(_) => None,
+    };
+    if let Some(result) = linear {
+        let solution = result
+            .solve(desired_result, 0..100)
+            .ok_or("Unable to find valid noun and verb inputs")?;
+        let value = |name| *solution.get(name).unwrap_or(&0);
+        return Ok((value("noun"), value("verb")));
+    }
+
+    let program = CompiledProgram::with_slots(initial_memory, &[1, 2]);
     for noun in 0..100 {
         for verb in 0..100 {
-            match aoc2019::intcode::perform_computation(noun, verb, &initial_memory)? {
+            match program.run(&[(1, noun), (2, verb)], &[])?.memory[0] {
                 result if result == desired_result => {
                     return Ok((noun, verb));
                 }
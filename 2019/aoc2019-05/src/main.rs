@@ -14,10 +14,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn part1(input: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut initial_memory = aoc2019::intcode::parse_intcode_program(input)?;
+    let initial_memory = aoc2019::intcode::parse_intcode_program(input)?;
 
-    let mut vm = IntcodeVM::new(&mut initial_memory, Some(|_last_output| 1));
-    let outputs = vm.run();
+    let mut vm = IntcodeVM::new(initial_memory, Some(|_last_output| 1));
+    let outputs = vm.run()?;
 
     writeln!(io::stdout(), "outputs: {:?}", outputs)?;
 
@@ -25,10 +25,10 @@ fn part1(input: &str) -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn part2(input: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut initial_memory = aoc2019::intcode::parse_intcode_program(input)?;
+    let initial_memory = aoc2019::intcode::parse_intcode_program(input)?;
 
-    let mut vm = IntcodeVM::new(&mut initial_memory, Some(|_last_output| 5));
-    let outputs = vm.run();
+    let mut vm = IntcodeVM::new(initial_memory, Some(|_last_output| 5));
+    let outputs = vm.run()?;
 
     writeln!(io::stdout(), "outputs: {:?}", outputs)?;
 
@@ -0,0 +1,175 @@
+//! Chains of Intcode amplifiers, each feeding its output signal to the next.
+use crate::intcode::{IntcodeError, IntcodeVM, RunState};
+use std::collections::VecDeque;
+
+pub struct AmplifierChain {
+    program: Vec<i64>,
+    feedback: bool,
+}
+
+impl AmplifierChain {
+    /// A chain that passes the signal through every amplifier once.
+    pub fn new(program: Vec<i64>) -> Self {
+        Self {
+            program,
+            feedback: false,
+        }
+    }
+
+    /// A chain whose last amplifier feeds back into the first until they have all halted.
+    pub fn with_feedback(program: Vec<i64>) -> Self {
+        Self {
+            program,
+            feedback: true,
+        }
+    }
+
+    /// Runs one amplifier per phase setting on an input signal of 0 and returns the
+    /// final signal out of the last amplifier.
+    pub fn run(&self, phases: &[i64]) -> Result<i64, IntcodeError> {
+        let mut amplifiers = phases
+            .iter()
+            .map(|phase| {
+                let mut vm = IntcodeVM::new(self.program.clone(), None);
+                vm.push_input(*phase);
+                vm
+            })
+            .collect::<Vec<_>>();
+
+        let mut signal = 0;
+        loop {
+            let mut halted = true;
+            for vm in amplifiers.iter_mut() {
+                vm.push_input(signal);
+                let mut outputs = VecDeque::new();
+                let state = vm.run_with(&mut VecDeque::new(), &mut outputs)?;
+                match outputs.pop_back() {
+                    Some(output) => signal = output,
+                    // waiting on a signal that will never come
+                    None if state == RunState::AwaitingInput => {
+                        return Err(IntcodeError::InputStarvation {
+                            address: vm.instruction_pointer(),
+                        })
+                    }
+                    None => {}
+                }
+                halted &= state == RunState::Halted;
+            }
+            if !self.feedback || halted {
+                return Ok(signal);
+            }
+        }
+    }
+
+    /// Tries every ordering of `phases` and returns the strongest signal along with the
+    /// ordering that produced it.
+    pub fn best_signal(&self, phases: &[i64]) -> Result<Option<(i64, Vec<i64>)>, IntcodeError> {
+        let mut best: Option<(i64, Vec<i64>)> = None;
+        for ordering in permutations(phases) {
+            let signal = self.run(&ordering)?;
+            let stronger = match &best {
+                Some((strongest, _)) => signal > *strongest,
+                None => true,
+            };
+            if stronger {
+                best = Some((signal, ordering));
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// Every ordering of `items`, generated with Heap's algorithm.
+fn permutations(items: &[i64]) -> Vec<Vec<i64>> {
+    let mut items = items.to_vec();
+    let mut counters = vec![0; items.len()];
+    let mut orderings = vec![items.clone()];
+    let mut i = 0;
+    while i < items.len() {
+        if counters[i] < i {
+            match i % 2 {
+                0 => items.swap(0, i),
+                _ => items.swap(counters[i], i),
+            }
+            orderings.push(items.clone());
+            counters[i] += 1;
+            i = 0;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+    orderings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parse_intcode_program;
+
+    #[test]
+    fn test_permutations() {
+        let mut orderings = permutations(&[1, 2, 3]);
+        orderings.sort();
+        assert_eq!(
+            orderings,
+            [
+                [1, 2, 3],
+                [1, 3, 2],
+                [2, 1, 3],
+                [2, 3, 1],
+                [3, 1, 2],
+                [3, 2, 1]
+            ]
+        );
+        assert_eq!(permutations(&[0, 1, 2, 3, 4]).len(), 120);
+    }
+
+    #[test]
+    fn test_chain() {
+        let program =
+            parse_intcode_program("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0").unwrap();
+        let chain = AmplifierChain::new(program);
+        assert_eq!(chain.run(&[4, 3, 2, 1, 0]).unwrap(), 43210);
+        assert_eq!(
+            chain.best_signal(&[0, 1, 2, 3, 4]).unwrap(),
+            Some((43210, vec![4, 3, 2, 1, 0]))
+        );
+
+        let program = parse_intcode_program(
+            "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+        )
+        .unwrap();
+        let chain = AmplifierChain::new(program);
+        assert_eq!(
+            chain.best_signal(&[0, 1, 2, 3, 4]).unwrap(),
+            Some((54321, vec![0, 1, 2, 3, 4]))
+        );
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        let program = parse_intcode_program(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,\
+             4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        )
+        .unwrap();
+        let chain = AmplifierChain::with_feedback(program);
+        assert_eq!(chain.run(&[9, 8, 7, 6, 5]).unwrap(), 139629729);
+        assert_eq!(
+            chain.best_signal(&[5, 6, 7, 8, 9]).unwrap(),
+            Some((139629729, vec![9, 8, 7, 6, 5]))
+        );
+    }
+
+    #[test]
+    fn test_starved_amplifier() {
+        // reads a second signal that never arrives
+        let program = parse_intcode_program("3,0,3,0,3,0,99").unwrap();
+        let chain = AmplifierChain::new(program);
+        assert_eq!(
+            chain.run(&[1, 2]),
+            Err(IntcodeError::InputStarvation { address: 4 })
+        );
+    }
+}
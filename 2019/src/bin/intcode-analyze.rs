@@ -0,0 +1,30 @@
+use aoc2019::intcode::{analysis, parse_intcode_program};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+
+/// Prints a static analysis report for an Intcode program, read from the file given as
+/// an argument or from stdin.
+///
+/// usage: intcode-analyze [program.txt] [--dot]
+/// `--dot` prints the control-flow graph in Graphviz DOT format instead.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let dot = args.iter().any(|arg| arg == "--dot");
+    let mut input = String::new();
+    match args.iter().find(|arg| *arg != "--dot") {
+        Some(path) => input = fs::read_to_string(path)?,
+        None => {
+            io::stdin().read_to_string(&mut input)?;
+        }
+    }
+
+    let memory = parse_intcode_program(&input)?;
+    let analysis = analysis::analyze(&memory);
+    match dot {
+        true => write!(io::stdout(), "{}", analysis.to_dot())?,
+        false => write!(io::stdout(), "{}", analysis)?,
+    }
+
+    Ok(())
+}
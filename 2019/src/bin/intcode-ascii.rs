@@ -0,0 +1,36 @@
+use aoc2019::intcode::ascii::AsciiVM;
+use aoc2019::intcode::{parse_intcode_program, IntcodeVM, RunState};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+/// Plays an ASCII Intcode program (a text adventure, say) by hand: whatever it prints goes
+/// to stdout and every line typed on stdin is sent back as its next command.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args()
+        .nth(1)
+        .ok_or("usage: intcode-ascii <program.txt>")?;
+    let memory = parse_intcode_program(&fs::read_to_string(path)?)?;
+    let mut ascii = AsciiVM::new(IntcodeVM::new(memory, None));
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut stdout = io::stdout();
+    let mut output = ascii.run()?;
+    loop {
+        write!(stdout, "{}", output.text)?;
+        if let Some(answer) = output.answer {
+            writeln!(stdout, "[answer: {}]", answer)?;
+        }
+        stdout.flush()?;
+        if output.state == RunState::Halted {
+            break;
+        }
+        match lines.next() {
+            Some(line) => output = ascii.execute(&line?)?,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use aoc2019::intcode::asm::assemble;
+use aoc2019::intcode::compile::CompiledProgram;
+use aoc2019::intcode::{original, parse_intcode_program, IntcodeVM};
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Sums 1..=n in a tight loop, so nearly all of the time goes to decoding and executing
+/// the same handful of instructions.
+const LOOP_SOURCE: &str = "
+        IN   [n]
+loop:   JF   [n], #done
+        ADD  [sum], [n], [sum]
+        ADD  [n], #-1, [n]
+        JT   #1, #loop
+done:   OUT  [sum]
+        HLT
+    n:  .data 0
+  sum:  .data 0
+";
+
+const LOOP_ITERATIONS: i64 = 5_000_000;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    /// The string-formatting interpreter loop the VM started out with.
+    Original,
+    Uncached,
+    /// The cache as `IntcodeVM::new` sets it up, filling from the first backward jump.
+    Default,
+    Cached,
+    Compiled,
+}
+
+const MODES: [Mode; 5] = [
+    Mode::Original,
+    Mode::Uncached,
+    Mode::Default,
+    Mode::Cached,
+    Mode::Compiled,
+];
+
+fn interpreter(memory: Vec<i64>, mode: Mode) -> IntcodeVM {
+    let mut vm = IntcodeVM::new(memory, None);
+    match mode {
+        Mode::Uncached => vm.set_decode_cache(false),
+        Mode::Cached => vm.set_decode_cache(true),
+        Mode::Original | Mode::Default | Mode::Compiled => {}
+    }
+    vm
+}
+
+/// Day 2 style sweep: runs the program once for every noun/verb pair.
+fn sweep(memory: &[i64], mode: Mode) -> Duration {
+    let timer = Instant::now();
+    let compiled = CompiledProgram::with_slots(memory, &[1, 2]);
+    for noun in 0..100 {
+        for verb in 0..100 {
+            // some pairs send the program off the rails; only the time matters here
+            if let Mode::Compiled = mode {
+                let _ = compiled.run(&[(1, noun), (2, verb)], &[]);
+                continue;
+            }
+            let mut memory = memory.to_vec();
+            memory[1] = noun;
+            memory[2] = verb;
+            let _ = match mode {
+                Mode::Original => original::run(memory, &[]),
+                _ => interpreter(memory, mode).run(),
+            };
+        }
+    }
+    timer.elapsed()
+}
+
+fn count_loop(memory: &[i64], mode: Mode) -> Result<Duration, Box<dyn std::error::Error>> {
+    let timer = Instant::now();
+    let outputs = match mode {
+        Mode::Compiled => {
+            CompiledProgram::compile(memory)
+                .run(&[], &[LOOP_ITERATIONS])?
+                .outputs
+        }
+        Mode::Original => original::run(memory.to_vec(), &[LOOP_ITERATIONS])?,
+        _ => {
+            let mut vm = interpreter(memory.to_vec(), mode);
+            vm.push_input(LOOP_ITERATIONS);
+            vm.run()?
+        }
+    };
+    let elapsed = timer.elapsed();
+    assert_eq!(outputs, [LOOP_ITERATIONS * (LOOP_ITERATIONS + 1) / 2]);
+    Ok(elapsed)
+}
+
+fn report(name: &str, timings: &[Duration]) {
+    let baseline = timings[0].as_secs_f64();
+    let columns = timings
+        .iter()
+        .map(|timing| {
+            format!(
+                "{:>10.2?} ({:.2}x)",
+                timing,
+                baseline / timing.as_secs_f64()
+            )
+        })
+        .collect::<Vec<_>>();
+    println!("{:<24} {}", name, columns.join("  "));
+}
+
+/// Times the original string-formatting interpreter, then the current one with its decode
+/// cache off, as `IntcodeVM::new` sets it up and forced on, then compiled. Speedups are
+/// relative to the original.
+///
+/// usage: intcode-bench [day2-program.txt]
+/// Build with `--release` for meaningful numbers.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "aoc2019-02/input.txt".to_string());
+    let day2 = parse_intcode_program(&fs::read_to_string(path)?)?;
+    println!(
+        "{:<24} {:>18}  {:>18}  {:>18}  {:>18}  {:>18}",
+        "", "original", "cache off", "default", "cache on", "compiled"
+    );
+    let timings = MODES
+        .iter()
+        .map(|mode| sweep(&day2, *mode))
+        .collect::<Vec<_>>();
+    report("day 2 sweep (10000 runs)", &timings);
+
+    let counter = assemble(LOOP_SOURCE)?;
+    let timings = MODES
+        .iter()
+        .map(|mode| count_loop(&counter, *mode))
+        .collect::<Result<Vec<_>, _>>()?;
+    report("counting loop", &timings);
+    println!("(speedups are relative to the original string-formatting decoder)");
+
+    Ok(())
+}
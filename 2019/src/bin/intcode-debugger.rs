@@ -0,0 +1,20 @@
+use aoc2019::intcode::{parse_intcode_program, IntcodeVM};
+use aoc_vm::debugger::{self, Debugger, DEFAULT_HISTORY};
+use std::env;
+use std::fs;
+use std::io;
+
+/// Interactive debugger for the Intcode program given as the first argument.
+/// Commands are read from stdin; type `help` for a list. History for reverse stepping is
+/// recorded from the start.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args()
+        .nth(1)
+        .ok_or("usage: intcode-debugger <program.txt>")?;
+    let memory = parse_intcode_program(&fs::read_to_string(path)?)?;
+    let mut debugger = Debugger::new(IntcodeVM::new(memory, None));
+    debugger.vm.start_recording(DEFAULT_HISTORY);
+
+    debugger::repl(&mut debugger, io::stdin().lock(), io::stdout())?;
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use aoc2019::intcode::{disasm, parse_intcode_program};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+
+/// Prints an annotated listing of an Intcode program, read from the file given as the
+/// first argument or from stdin.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = String::new();
+    match env::args().nth(1) {
+        Some(path) => input = fs::read_to_string(path)?,
+        None => {
+            io::stdin().read_to_string(&mut input)?;
+        }
+    }
+
+    let memory = parse_intcode_program(&input)?;
+    write!(io::stdout(), "{}", disasm::disassemble(&memory))?;
+
+    Ok(())
+}
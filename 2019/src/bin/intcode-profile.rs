@@ -0,0 +1,59 @@
+use aoc2019::intcode::{parse_intcode_program, IntcodeVM};
+use aoc_vm::trace::{trace, Profiler};
+use aoc_vm::Step;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+
+/// Runs an Intcode program and prints a profile of the instructions it executed.
+///
+/// usage: intcode-profile <program.txt> [input,input,..] [--trace]
+/// `--trace` additionally writes every executed instruction to stderr.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let show_trace = args.iter().any(|arg| arg == "--trace");
+    let mut args = args.iter().filter(|arg| *arg != "--trace");
+    let path = args
+        .next()
+        .ok_or("usage: intcode-profile <program.txt> [input,input,..] [--trace]")?;
+    let mut inputs = match args.next() {
+        Some(list) => parse_intcode_program(list)?,
+        None => Vec::new(),
+    }
+    .into_iter()
+    .collect::<VecDeque<_>>();
+
+    let memory = parse_intcode_program(&fs::read_to_string(path)?)?;
+    let mut vm = IntcodeVM::new(memory, None);
+    let mut profiler = Profiler::new();
+    let mut outputs = Vec::new();
+    let mut stderr = io::stderr();
+    let waiting = loop {
+        let stop = trace(&mut vm, usize::MAX, |line| {
+            profiler.record(line);
+            if let Step::Output(value) = line.step {
+                outputs.push(value);
+            }
+            if show_trace {
+                let _ = writeln!(stderr, "{}", line);
+            }
+        })?;
+        match stop {
+            Step::AwaitingInput => match inputs.pop_front() {
+                Some(value) => vm.push_input(value),
+                None => break true,
+            },
+            _ => break false,
+        }
+    };
+
+    let mut stdout = io::stdout();
+    writeln!(stdout, "outputs: {:?}", outputs)?;
+    if waiting {
+        writeln!(stdout, "(stopped waiting for input)")?;
+    }
+    write!(stdout, "{}", profiler)?;
+
+    Ok(())
+}
@@ -1,4 +1,28 @@
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+
+pub mod analysis;
+pub mod ascii;
+pub mod asm;
+pub mod compile;
+pub mod device;
+pub mod disasm;
+#[cfg(test)]
+mod fuzz;
+pub mod io;
+pub mod journal;
+pub mod original;
+pub mod snapshot;
+pub mod symbolic;
+mod vm;
+
+use self::device::MappedDevice;
+pub use self::io::{InputSource, OutputSink};
+use self::journal::{Entry, Journal};
+use aoc_vm::MemoryWrite;
+
+/// Writes beyond this many words are rejected rather than growing memory without bound.
+const MAX_MEMORY: usize = 1 << 24;
 
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 enum OpCode {
@@ -10,301 +34,633 @@ enum OpCode {
     JumpIfFalse,
     LessThan,
     Equals,
+    AdjustRelativeBase,
     Halt,
 }
 
-lazy_static! {
-    static ref OP_CODES: HashMap<usize, (OpCode, usize)> = hashmap! {
-        1 => (OpCode::Add, 3),
-        2 => (OpCode::Multiply, 3),
-        3 => (OpCode::Input, 1),
-        4 => (OpCode::Output, 1),
-        5 => (OpCode::JumpIfTrue, 2),
-        6 => (OpCode::JumpIfFalse, 2),
-        7 => (OpCode::LessThan, 3),
-        8 => (OpCode::Equals, 3),
-        99 => (OpCode::Halt, 0),
-    };
+impl OpCode {
+    fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::Add => "ADD",
+            OpCode::Multiply => "MUL",
+            OpCode::Input => "IN",
+            OpCode::Output => "OUT",
+            OpCode::JumpIfTrue => "JT",
+            OpCode::JumpIfFalse => "JF",
+            OpCode::LessThan => "LT",
+            OpCode::Equals => "EQ",
+            OpCode::AdjustRelativeBase => "ARB",
+            OpCode::Halt => "HLT",
+        }
+    }
+
+    /// Whether the parameter at `index` is a write destination (and so can't be immediate).
+    fn writes_parameter(self, index: usize) -> bool {
+        match self {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => index == 2,
+            OpCode::Input => index == 0,
+            _ => false,
+        }
+    }
+}
+
+/// Opcode value, opcode and argument count for every instruction.
+const OP_CODES: [(usize, OpCode, usize); 10] = [
+    (1, OpCode::Add, 3),
+    (2, OpCode::Multiply, 3),
+    (3, OpCode::Input, 1),
+    (4, OpCode::Output, 1),
+    (5, OpCode::JumpIfTrue, 2),
+    (6, OpCode::JumpIfFalse, 2),
+    (7, OpCode::LessThan, 3),
+    (8, OpCode::Equals, 3),
+    (9, OpCode::AdjustRelativeBase, 1),
+    (99, OpCode::Halt, 0),
+];
+
+/// `OP_CODES` indexed by opcode value, for decoding without a hash lookup.
+const OP_CODE_TABLE: [Option<(OpCode, usize)>; 100] = build_op_code_table();
+
+const fn build_op_code_table() -> [Option<(OpCode, usize)>; 100] {
+    let mut table = [None; 100];
+    let mut i = 0;
+    while i < OP_CODES.len() {
+        let (value, op_code, argument_count) = OP_CODES[i];
+        table[value] = Some((op_code, argument_count));
+        i += 1;
+    }
+    table
 }
 
-pub struct IntcodeVM<'a> {
-    pub program_memory: &'a mut Vec<i32>,
-    pub handle_input: Option<fn(Option<&i32>) -> i32>,
+/// A fully decoded instruction. Only the first `argument_count` modes and
+/// parameters are meaningful.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Instruction {
+    op_code: OpCode,
+    argument_count: usize,
+    param_modes: [u8; 3],
+    parameters: [i64; 3],
+}
+
+impl Instruction {
+    fn len(&self) -> usize {
+        1 + self.argument_count
+    }
+
+    fn param_modes(&self) -> &[u8] {
+        &self.param_modes[..self.argument_count]
+    }
+
+    fn parameters(&self) -> &[i64] {
+        &self.parameters[..self.argument_count]
+    }
+}
+
+/// Everything that can go wrong while executing an Intcode program.
+/// `address` is always the address of the offending instruction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IntcodeError {
+    UnknownOpCode { value: i64, address: usize },
+    InvalidParameterMode { mode: i64, address: usize },
+    ImmediateModeWrite { address: usize },
+    NegativeAddress { target: i64, address: usize },
+    AddressOutOfRange { target: i64, address: usize },
+    ArithmeticOverflow { address: usize },
+    InputStarvation { address: usize },
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOpCode { value, address } => {
+                write!(f, "unknown opcode {} at address {}", value, address)
+            }
+            IntcodeError::InvalidParameterMode { mode, address } => {
+                write!(f, "invalid parameter mode {} at address {}", mode, address)
+            }
+            IntcodeError::ImmediateModeWrite { address } => {
+                write!(
+                    f,
+                    "write parameter in immediate mode at address {}",
+                    address
+                )
+            }
+            IntcodeError::NegativeAddress { target, address } => {
+                write!(f, "negative address {} used at address {}", target, address)
+            }
+            IntcodeError::AddressOutOfRange { target, address } => {
+                write!(f, "address {} out of range at address {}", target, address)
+            }
+            IntcodeError::ArithmeticOverflow { address } => {
+                write!(f, "arithmetic overflow at address {}", address)
+            }
+            IntcodeError::InputStarvation { address } => {
+                write!(
+                    f,
+                    "input requested at address {}, but none available",
+                    address
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+/// The result of executing a single instruction with `IntcodeVM::step`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StepResult {
+    Executed,
+    Output(i64),
+    AwaitingInput,
+    Halted,
+}
+
+/// Why `IntcodeVM::run_with` stopped.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RunState {
+    Halted,
+    AwaitingInput,
+}
+
+pub struct IntcodeVM {
+    /// Editing code here directly requires `invalidate_decode_cache` before resuming.
+    pub program_memory: Vec<i64>,
+    pub handle_input: Option<fn(Option<&i64>) -> i64>,
     instruction_pointer: usize,
-    input: Option<i32>,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    /// Set through `aoc_vm::Vm::set_tracing`, to keep the operands and write of each step.
+    tracing: bool,
+    /// The values read by the last instruction, in order, while tracing.
+    traced_operands: Vec<i64>,
+    last_write: Option<MemoryWrite>,
+    decode_cache: Vec<Option<Instruction>>,
+    decode_cache_enabled: bool,
+    /// Set by the first backward jump; until then no code has run twice.
+    decode_cache_warm: bool,
+    devices: Vec<MappedDevice>,
+    journal: Option<Journal>,
 }
 
-impl<'a> IntcodeVM<'a> {
-    pub fn new(
-        mem: &'a mut std::vec::Vec<i32>,
-        handle_input: Option<fn(Option<&i32>) -> i32>,
-    ) -> Self {
+impl IntcodeVM {
+    pub fn new(mem: std::vec::Vec<i64>, handle_input: Option<fn(Option<&i64>) -> i64>) -> Self {
         Self {
             program_memory: mem,
-            handle_input: handle_input,
+            handle_input,
             instruction_pointer: 0,
-            input: None,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            tracing: false,
+            traced_operands: Vec::new(),
+            last_write: None,
+            decode_cache: Vec::new(),
+            decode_cache_enabled: true,
+            decode_cache_warm: false,
+            devices: Vec::new(),
+            journal: None,
         }
     }
-    pub fn run(&mut self) -> Vec<i32> {
-        let mut outputs: Vec<i32> = Vec::new();
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    /// Whether the next instruction is a halt.
+    pub fn is_halted(&self) -> bool {
+        let ip = self.instruction_pointer;
+        matches!(
+            parse_op_code(self.read_memory(ip), ip),
+            Ok((OpCode::Halt, ..))
+        )
+    }
+
+    /// Inputs queued but not yet consumed by the program.
+    pub fn pending_inputs(&self) -> &VecDeque<i64> {
+        &self.inputs
+    }
+
+    /// Reads memory the way the program would, so cells past the image read as zero.
+    /// Devices are bypassed, showing the plain cell underneath.
+    pub fn peek(&self, address: usize) -> i64 {
+        self.read_memory(address)
+    }
+
+    /// Writes memory the way the program would, growing it if needed. Devices are
+    /// bypassed, writing the plain cell underneath.
+    pub fn poke(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        self.write_memory(address, value)
+    }
+
+    /// Decoded instructions are cached per address (and dropped again when the program
+    /// writes over them). On by default, but the cache only starts filling at the first
+    /// backward jump, so straight-line runs like day 2's never pay for it. Turning it on
+    /// explicitly caches from the next instruction; turning it off also empties the cache.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache_enabled = enabled;
+        self.decode_cache_warm = enabled;
+        self.invalidate_decode_cache();
+    }
+
+    /// Must be called after editing code through `program_memory` directly;
+    /// `poke` and `restore` take care of this themselves.
+    pub fn invalidate_decode_cache(&mut self) {
+        self.decode_cache.clear();
+    }
+
+    /// Queues a value to be consumed by the next input instruction.
+    pub fn push_input(&mut self, value: i64) {
+        self.inputs.push_back(value);
+    }
+
+    pub fn run(&mut self) -> Result<Vec<i64>, IntcodeError> {
+        let mut outputs: Vec<i64> = Vec::new();
         while let Some(interrupt) = self.next() {
-            match interrupt {
+            match interrupt? {
                 Some(out) => outputs.push(out),
                 None => match self.handle_input {
                     Some(handler) => {
-                        self.input = Some(handler(outputs.last()));
+                        self.push_input(handler(outputs.last()));
                     }
                     None => {
-                        panic!("Input requested, but no handler specified");
+                        return Err(IntcodeError::InputStarvation {
+                            address: self.instruction_pointer,
+                        });
                     }
                 },
             };
         }
-        outputs
+        Ok(outputs)
     }
-}
 
-impl Iterator for IntcodeVM<'_> {
-    type Item = Option<i32>;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let (op_code, param_modes) =
-                parse_op_code(self.program_memory[self.instruction_pointer]).unwrap();
-            let argument_count = param_modes.len();
-            let parameters = get_parameters(
-                self.instruction_pointer,
-                argument_count,
-                &self.program_memory,
-            )
-            .unwrap();
+    /// Runs until the program halts, or until it needs input and `input` has none to give.
+    /// Queued inputs are consumed before asking `input`.
+    pub fn run_with<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<RunState, IntcodeError>
+    where
+        I: InputSource + ?Sized,
+        O: OutputSink + ?Sized,
+    {
+        while let Some(interrupt) = self.next() {
+            match interrupt? {
+                Some(out) => output.send_output(out),
+                None => match input.next_input() {
+                    Some(value) => self.push_input(value),
+                    None => return Ok(RunState::AwaitingInput),
+                },
+            }
+        }
+        Ok(RunState::Halted)
+    }
 
-            match op_code {
-                OpCode::Halt => break,
-                OpCode::Add => {
-                    let operand_1 = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let operand_2 = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let dest_pointer = *parameters.get(2).unwrap() as usize;
-                    self.program_memory[dest_pointer] = operand_1 + operand_2;
-                }
-                OpCode::Multiply => {
-                    let operand_1 = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let operand_2 = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let dest_pointer = *parameters.get(2).unwrap() as usize;
-                    self.program_memory[dest_pointer] = operand_1 * operand_2;
+    /// Executes exactly one instruction. Input instructions without pending input and the
+    /// halt instruction leave the instruction pointer where it is.
+    pub fn step(&mut self) -> Result<StepResult, IntcodeError> {
+        let ip = self.instruction_pointer;
+        let instruction = self.fetch(ip)?;
+
+        if !self.tracing && self.journal.is_none() {
+            return self.execute(&instruction);
+        }
+
+        // Operands are collected as the instruction reads them, so devices are read once
+        self.traced_operands.clear();
+        let relative_base = self.relative_base;
+        let memory_len = self.program_memory.len();
+        let input = self.inputs.front().copied();
+        self.last_write = None;
+        let result = self.execute(&instruction)?;
+        if let StepResult::Executed | StepResult::Output(_) = result {
+            self.journal_entry(Entry {
+                ip,
+                relative_base,
+                write: self.last_write,
+                input: input.filter(|_| instruction.op_code == OpCode::Input),
+                memory_len: Some(memory_len).filter(|len| *len != self.program_memory.len()),
+            });
+        }
+        Ok(result)
+    }
+
+    fn fetch(&mut self, ip: usize) -> Result<Instruction, IntcodeError> {
+        if !(self.decode_cache_enabled && self.decode_cache_warm) {
+            return decode_instruction(&self.program_memory, ip);
+        }
+        if let Some(Some(instruction)) = self.decode_cache.get(ip) {
+            return Ok(*instruction);
+        }
+        let instruction = decode_instruction(&self.program_memory, ip)?;
+        if ip >= self.decode_cache.len() {
+            let size = self.program_memory.len().max(ip + 1);
+            self.decode_cache.resize(size, None);
+        }
+        self.decode_cache[ip] = Some(instruction);
+        Ok(instruction)
+    }
+
+    /// Drops cached instructions that include the word at `address`.
+    fn invalidate_decoded(&mut self, address: usize) {
+        let end = (address + 1).min(self.decode_cache.len());
+        for start in address.saturating_sub(3)..end {
+            if let Some(instruction) = self.decode_cache[start] {
+                if start + instruction.len() > address {
+                    self.decode_cache[start] = None;
                 }
-                OpCode::Input => {
-                    match self.input {
-                        Some(val) => {
-                            let target = *parameters.get(0).unwrap() as usize;
-                            // println!("INPUT={} AT: {}", val, target);
-                            self.input = None;
-                            self.program_memory[target] = val;
-                        }
-                        None => return Some(None),
+            }
+        }
+    }
+
+    fn execute(&mut self, instruction: &Instruction) -> Result<StepResult, IntcodeError> {
+        let Instruction {
+            op_code,
+            argument_count,
+            param_modes,
+            parameters,
+        } = *instruction;
+        let mut result = StepResult::Executed;
+        match op_code {
+            OpCode::Halt => return Ok(StepResult::Halted),
+            OpCode::Add => {
+                let operand_1 = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let operand_2 = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let dest_pointer = self.resolve_address(parameters[2], param_modes[2])?;
+                let sum = operand_1.checked_add(operand_2).ok_or(self.overflow())?;
+                self.write(dest_pointer, sum)?;
+            }
+            OpCode::Multiply => {
+                let operand_1 = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let operand_2 = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let dest_pointer = self.resolve_address(parameters[2], param_modes[2])?;
+                let product = operand_1.checked_mul(operand_2).ok_or(self.overflow())?;
+                self.write(dest_pointer, product)?;
+            }
+            OpCode::Input => {
+                let target = self.resolve_address(parameters[0], param_modes[0])?;
+                match self.inputs.pop_front() {
+                    Some(val) => {
+                        self.write(target, val)?;
                     }
+                    None => return Ok(StepResult::AwaitingInput),
                 }
-                OpCode::Output => {
-                    let output = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    // println!("OUTPUT: {}", output);
-                    self.instruction_pointer += 2;
-
-                    return Some(Some(output));
-                }
-                OpCode::JumpIfTrue => {
-                    let condition = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let target = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap() as usize;
-                    if condition != 0 {
-                        self.instruction_pointer = target;
-                        continue;
-                    }
+            }
+            OpCode::Output => {
+                let output = self.resolve_parameter(parameters[0], param_modes[0])?;
+                result = StepResult::Output(output);
+            }
+            OpCode::JumpIfTrue => {
+                let condition = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let target = self.resolve_parameter(parameters[1], param_modes[1])?;
+                if condition != 0 {
+                    self.jump(target)?;
+                    return Ok(result);
                 }
-                OpCode::JumpIfFalse => {
-                    let condition = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let target = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap() as usize;
-                    if condition == 0 {
-                        self.instruction_pointer = target;
-                        continue;
-                    }
+            }
+            OpCode::JumpIfFalse => {
+                let condition = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let target = self.resolve_parameter(parameters[1], param_modes[1])?;
+                if condition == 0 {
+                    self.jump(target)?;
+                    return Ok(result);
                 }
-                OpCode::LessThan => {
-                    let first = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let second = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let target = *parameters.get(2).unwrap() as usize;
-
-                    match first < second {
-                        true => self.program_memory[target] = 1,
-                        false => self.program_memory[target] = 0,
-                    }
+            }
+            OpCode::LessThan => {
+                let first = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let second = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let target = self.resolve_address(parameters[2], param_modes[2])?;
+
+                match first < second {
+                    true => self.write(target, 1)?,
+                    false => self.write(target, 0)?,
                 }
-                OpCode::Equals => {
-                    let first = resolve_parameter(
-                        parameters.get(0).unwrap(),
-                        param_modes.get(0).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let second = resolve_parameter(
-                        parameters.get(1).unwrap(),
-                        param_modes.get(1).unwrap(),
-                        &self.program_memory,
-                    )
-                    .unwrap();
-                    let target = *parameters.get(2).unwrap() as usize;
-
-                    match first == second {
-                        true => self.program_memory[target] = 1,
-                        false => self.program_memory[target] = 0,
-                    }
+            }
+            OpCode::Equals => {
+                let first = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let second = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let target = self.resolve_address(parameters[2], param_modes[2])?;
+
+                match first == second {
+                    true => self.write(target, 1)?,
+                    false => self.write(target, 0)?,
                 }
             }
+            OpCode::AdjustRelativeBase => {
+                let offset = self.resolve_parameter(parameters[0], param_modes[0])?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or(self.overflow())?;
+            }
+        }
 
-            self.instruction_pointer += 1 + argument_count;
+        self.instruction_pointer += 1 + argument_count;
+        Ok(result)
+    }
+
+    fn jump(&mut self, target: i64) -> Result<(), IntcodeError> {
+        let target = self.checked_address(target)?;
+        // jumping back means code is about to run again, which is when caching pays off
+        if target <= self.instruction_pointer {
+            self.decode_cache_warm = true;
         }
-        None
+        self.instruction_pointer = target;
+        Ok(())
     }
-}
 
-fn get_parameters(
-    instruction_pointer: usize,
-    count: usize,
-    program_memory: &Vec<i32>,
-) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
-    let mut results = Vec::new();
-    for param_i in 1..=count {
-        let param_value = program_memory[instruction_pointer + param_i];
-        results.push(param_value);
-    }
-    Ok(results)
+    /// Reads a memory cell, or the device mapped over it.
+    fn read(&self, address: usize) -> i64 {
+        if !self.devices.is_empty() {
+            if let Some(mapped) = self.device_at(address) {
+                return mapped.read(address);
+            }
+        }
+        self.read_memory(address)
+    }
+
+    /// Reads a memory cell. Addresses past the loaded image read as zero.
+    fn read_memory(&self, address: usize) -> i64 {
+        *self.program_memory.get(address).unwrap_or(&0)
+    }
+
+    /// Writes a memory cell, or the device mapped over it.
+    fn write(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        if !self.devices.is_empty() {
+            if let Some(mapped) = self.device_at(address) {
+                mapped.write(address, value);
+                return Ok(());
+            }
+        }
+        self.write_memory(address, value)
+    }
+
+    /// Writes a memory cell, growing memory with zeros if the address is past the end.
+    fn write_memory(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        if address >= self.program_memory.len() {
+            if address >= MAX_MEMORY {
+                return Err(IntcodeError::AddressOutOfRange {
+                    target: address as i64,
+                    address: self.instruction_pointer,
+                });
+            }
+            self.program_memory.resize(address + 1, 0);
+        }
+        if !self.decode_cache.is_empty() {
+            self.invalidate_decoded(address);
+        }
+        if self.tracing || self.journal.is_some() {
+            self.last_write = Some(MemoryWrite {
+                address,
+                old: self.program_memory[address],
+                new: value,
+            });
+        }
+        self.program_memory[address] = value;
+        Ok(())
+    }
+
+    fn resolve_parameter(&mut self, param_value: i64, param_mode: u8) -> Result<i64, IntcodeError> {
+        let value = match param_mode {
+            1 => param_value,
+            _ => self.read(self.resolve_address(param_value, param_mode)?),
+        };
+        if self.tracing {
+            self.traced_operands.push(value);
+        }
+        Ok(value)
+    }
+
+    /// Resolves the memory address a position-mode (0) or relative-mode (2) parameter refers to.
+    fn resolve_address(&self, param_value: i64, param_mode: u8) -> Result<usize, IntcodeError> {
+        let address = match param_mode {
+            0 => param_value,
+            2 => self
+                .relative_base
+                .checked_add(param_value)
+                .ok_or(self.overflow())?,
+            _ => {
+                return Err(IntcodeError::ImmediateModeWrite {
+                    address: self.instruction_pointer,
+                })
+            }
+        };
+        self.checked_address(address)
+    }
+
+    fn checked_address(&self, target: i64) -> Result<usize, IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                target,
+                address: self.instruction_pointer,
+            });
+        }
+        Ok(target as usize)
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::ArithmeticOverflow {
+            address: self.instruction_pointer,
+        }
+    }
 }
 
-fn resolve_parameter(
-    param_value: &i32,
-    param_mode: &usize,
-    program_memory: &Vec<i32>,
-) -> Result<i32, Box<dyn std::error::Error>> {
-    match param_mode {
-        0 => Ok(program_memory[(*param_value) as usize]),
-        1 => Ok(*param_value),
-        _ => Err("Bad mode".into()),
+/// Runs until the next output (`Some(Ok(Some(value)))`), until input is needed
+/// (`Some(Ok(None))`) or until the program halts (`None`). A failing instruction is
+/// reported as `Some(Err(..))` and is not skipped, so it will be reported again if polled.
+impl Iterator for IntcodeVM {
+    type Item = Result<Option<i64>, IntcodeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step() {
+                Ok(StepResult::Executed) => continue,
+                Ok(StepResult::Output(output)) => return Some(Ok(Some(output))),
+                Ok(StepResult::AwaitingInput) => return Some(Ok(None)),
+                Ok(StepResult::Halted) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
     }
 }
 
-fn build_parameter_modes(
-    num_parameters: usize,
-    param_mode_data: Vec<usize>,
-) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
-    let mut modes = Vec::new();
-    for i in 0..num_parameters {
-        modes.push(*param_mode_data.get(i).unwrap_or(&0));
+/// Splits an instruction word into its opcode, argument count and parameter modes.
+fn parse_op_code(input: i64, address: usize) -> Result<(OpCode, usize, [u8; 3]), IntcodeError> {
+    let unknown = IntcodeError::UnknownOpCode {
+        value: input,
+        address,
+    };
+    if input < 0 {
+        return Err(unknown);
+    }
+    let (op_code, argument_count) = OP_CODE_TABLE[(input % 100) as usize].ok_or(unknown)?;
+
+    let mut param_modes = [0; 3];
+    let mut mode_data = input / 100;
+    for mode in param_modes.iter_mut().take(argument_count) {
+        let digit = mode_data % 10;
+        if digit > 2 {
+            return Err(IntcodeError::InvalidParameterMode {
+                mode: digit,
+                address,
+            });
+        }
+        *mode = digit as u8;
+        mode_data /= 10;
     }
-    Ok(modes)
+    Ok((op_code, argument_count, param_modes))
 }
 
-fn parse_op_code(input: i32) -> Result<(OpCode, Vec<usize>), Box<dyn std::error::Error>> {
-    let mut digits_reverse = input
-        .to_string()
-        .chars()
-        .map(|d| d.to_digit(10).unwrap() as usize)
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev();
-    let ones = digits_reverse.next().unwrap();
-    let tens = digits_reverse.next().unwrap_or(0);
-    let param_mode_data = digits_reverse.collect::<Vec<usize>>();
-
-    let op_code_value = tens * 10 + ones;
-    // println!("OPCODE {} {:?}", op_code_value, input);
-    let (op_code, argument_count) = OP_CODES.get(&op_code_value).unwrap();
-    Ok((
-        *op_code,
-        build_parameter_modes(*argument_count, param_mode_data)?,
-    ))
+/// Decodes the instruction at `address`. Parameters past the end of memory read as zero.
+fn decode_instruction(memory: &[i64], address: usize) -> Result<Instruction, IntcodeError> {
+    let word = *memory.get(address).unwrap_or(&0);
+    let (op_code, argument_count, param_modes) = parse_op_code(word, address)?;
+    let mut parameters = [0; 3];
+    for (i, parameter) in parameters.iter_mut().take(argument_count).enumerate() {
+        *parameter = *memory.get(address + 1 + i).unwrap_or(&0);
+    }
+    Ok(Instruction {
+        op_code,
+        argument_count,
+        param_modes,
+        parameters,
+    })
 }
 
-pub fn parse_intcode_program(input: &str) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
-    Ok(input
-        .split(",")
-        .map(|n| n.trim().parse::<i32>().unwrap())
-        .collect::<Vec<i32>>())
+pub fn parse_intcode_program(input: &str) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    let program = input
+        .split(',')
+        .map(|n| n.trim().parse::<i64>())
+        .collect::<Result<Vec<i64>, _>>()?;
+    Ok(program)
 }
 
 pub fn run_intcode_program(
-    program_memory: &mut Vec<i32>,
-) -> Result<&Vec<i32>, Box<dyn std::error::Error>> {
-    let mut vm = IntcodeVM::new(program_memory, None);
-    vm.run();
-    Ok(vm.program_memory)
+    program_memory: &mut Vec<i64>,
+) -> Result<&Vec<i64>, Box<dyn std::error::Error>> {
+    let mut vm = IntcodeVM::new(std::mem::take(program_memory), None);
+    let result = vm.run();
+    *program_memory = vm.program_memory;
+    result?;
+    Ok(program_memory)
 }
 
 pub fn perform_computation(
-    noun: i32,
-    verb: i32,
-    initial_memory: &Vec<i32>,
-) -> Result<i32, Box<dyn std::error::Error>> {
-    let mut program_memory = initial_memory.clone();
+    noun: i64,
+    verb: i64,
+    initial_memory: &[i64],
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let mut program_memory = initial_memory.to_vec();
 
     // Add 'noun' / 'verb' parameters
     program_memory[1] = noun;
     program_memory[2] = verb;
 
-    let mut vm = IntcodeVM::new(&mut program_memory, None);
-    vm.run();
+    let mut vm = IntcodeVM::new(program_memory, None);
+    vm.run()?;
 
     Ok(vm.program_memory[0])
 }
@@ -324,9 +680,9 @@ mod tests {
 
     #[test]
     fn test_sample_1_struct() {
-        let mut memory = parse_intcode_program(MOCK_INPUT_1).unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, None);
-        vm.run();
+        let memory = parse_intcode_program(MOCK_INPUT_1).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.run().unwrap();
         assert_eq!(
             vm.program_memory[..],
             [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
@@ -381,97 +737,494 @@ mod tests {
 
     #[test]
     fn test_eq_8_position() {
-        let mut memory = parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 8));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 8));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 7));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 7));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_eq_8_immediate() {
-        let mut memory = parse_intcode_program("3,3,1108,-1,8,3,4,3,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 8));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1108,-1,8,3,4,3,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 8));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,3,1108,-1,8,3,4,3,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 7));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1108,-1,8,3,4,3,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 7));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_lt_8_position() {
-        let mut memory = parse_intcode_program("3,9,7,9,10,9,4,9,99,-1,8").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 2));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,9,7,9,10,9,4,9,99,-1,8").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 2));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,9,7,9,10,9,4,9,99,-1,8").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 9));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,9,7,9,10,9,4,9,99,-1,8").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 9));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_lt_8_immediate() {
-        let mut memory = parse_intcode_program("3,3,1107,-1,8,3,4,3,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 3));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1107,-1,8,3,4,3,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 3));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,3,1107,-1,8,3,4,3,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 10));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1107,-1,8,3,4,3,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 10));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_jump_nonzero_position() {
-        let mut memory = parse_intcode_program("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 1));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 1));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 0));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 0));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_jump_nonzero_immediate() {
-        let mut memory = parse_intcode_program("3,3,1105,-1,9,1101,0,0,12,4,12,99,1").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 2));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1105,-1,9,1101,0,0,12,4,12,99,1").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 2));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1]);
 
-        let mut memory = parse_intcode_program("3,3,1105,-1,9,1101,0,0,12,4,12,99,1").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 0));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,3,1105,-1,9,1101,0,0,12,4,12,99,1").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 0));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [0]);
     }
 
     #[test]
     fn test_jump_eq_8() {
-        let mut memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 4));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 4));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [999]);
 
-        let mut memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 8));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 8));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1000]);
 
-        let mut memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
-        let mut vm = IntcodeVM::new(&mut memory, Some(|_| 10));
-        let outputs = vm.run();
+        let memory = parse_intcode_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 10));
+        let outputs = vm.run().unwrap();
         assert_eq!(outputs[..], [1001]);
     }
+
+    #[test]
+    fn test_relative_base_quine() {
+        let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let memory = parse_intcode_program(program).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let outputs = vm.run().unwrap();
+        assert_eq!(outputs, parse_intcode_program(program).unwrap());
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let memory = parse_intcode_program("1102,34915192,34915192,7,4,7,99,0").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let outputs = vm.run().unwrap();
+        assert_eq!(outputs[..], [1219070632396864]);
+        assert_eq!(outputs[0].to_string().len(), 16);
+
+        let memory = parse_intcode_program("104,1125899906842624,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let outputs = vm.run().unwrap();
+        assert_eq!(outputs[..], [1125899906842624]);
+    }
+
+    #[test]
+    fn test_memory_grows_on_demand() {
+        // read an address past the image (reads 0), write far past the end
+        let memory = parse_intcode_program("1,100,5,1000,4,1000,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let outputs = vm.run().unwrap();
+        assert_eq!(outputs[..], [1000]);
+        assert_eq!(vm.program_memory.len(), 1001);
+        assert_eq!(vm.program_memory[1000], 1000);
+    }
+
+    #[test]
+    fn test_relative_mode_write() {
+        // rb += 10; input -> [rb+2]; output [12]
+        let memory = parse_intcode_program("109,10,203,2,4,12,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, Some(|_| 42));
+        let outputs = vm.run().unwrap();
+        assert_eq!(outputs[..], [42]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let run = |program: &str| {
+            let memory = parse_intcode_program(program).unwrap();
+            let mut vm = IntcodeVM::new(memory, None);
+            vm.run()
+        };
+        assert_eq!(
+            run("1,0,0,0,42"),
+            Err(IntcodeError::UnknownOpCode {
+                value: 42,
+                address: 4
+            })
+        );
+        assert_eq!(
+            run("-1"),
+            Err(IntcodeError::UnknownOpCode {
+                value: -1,
+                address: 0
+            })
+        );
+        assert_eq!(
+            run("301,0,0,0,99"),
+            Err(IntcodeError::InvalidParameterMode {
+                mode: 3,
+                address: 0
+            })
+        );
+        assert_eq!(
+            run("11101,1,1,0,99"),
+            Err(IntcodeError::ImmediateModeWrite { address: 0 })
+        );
+        assert_eq!(
+            run("1,-5,0,0,99"),
+            Err(IntcodeError::NegativeAddress {
+                target: -5,
+                address: 0
+            })
+        );
+        assert_eq!(
+            run("1105,1,-2"),
+            Err(IntcodeError::NegativeAddress {
+                target: -2,
+                address: 0
+            })
+        );
+        assert_eq!(
+            run("1101,1,1,999999999999,99"),
+            Err(IntcodeError::AddressOutOfRange {
+                target: 999999999999,
+                address: 0
+            })
+        );
+        assert_eq!(
+            run("1102,9223372036854775807,2,0,99"),
+            Err(IntcodeError::ArithmeticOverflow { address: 0 })
+        );
+        assert_eq!(
+            run("104,7,3,0,99"),
+            Err(IntcodeError::InputStarvation { address: 2 })
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let memory = parse_intcode_program("1101,2,3,0,3,5,4,0,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        assert_eq!(vm.step(), Ok(StepResult::Executed));
+        assert_eq!(vm.step(), Ok(StepResult::AwaitingInput));
+        assert_eq!(vm.step(), Ok(StepResult::AwaitingInput));
+        assert_eq!(vm.next(), Some(Ok(None)));
+        vm.push_input(7);
+        assert_eq!(vm.step(), Ok(StepResult::Executed));
+        assert_eq!(vm.step(), Ok(StepResult::Output(5)));
+        assert_eq!(vm.step(), Ok(StepResult::Halted));
+        assert_eq!(vm.next(), None);
+        assert_eq!(vm.program_memory[5], 7);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse_intcode_program("1,2,x,4").is_err());
+    }
+
+    static AMPLIFIER_PROGRAM: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+    static FEEDBACK_PROGRAM: &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,\
+                                     4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+    #[test]
+    fn test_run_with_queues() {
+        let mut signal = 0;
+        for phase in [4, 3, 2, 1, 0].iter() {
+            let memory = parse_intcode_program(AMPLIFIER_PROGRAM).unwrap();
+            let mut vm = IntcodeVM::new(memory, None);
+            let mut input: VecDeque<i64> = vec![*phase, signal].into_iter().collect();
+            let mut output = Vec::new();
+            let state = vm.run_with(&mut input, &mut output).unwrap();
+            assert_eq!(state, RunState::Halted);
+            signal = output[0];
+        }
+        assert_eq!(signal, 43210);
+    }
+
+    #[test]
+    fn test_run_with_feedback_loop() {
+        let phases = [9, 8, 7, 6, 5];
+        let memory = parse_intcode_program(FEEDBACK_PROGRAM).unwrap();
+        let mut vms = phases
+            .iter()
+            .map(|_| IntcodeVM::new(memory.clone(), None))
+            .collect::<Vec<_>>();
+        let mut queues = phases
+            .iter()
+            .map(|phase| vec![*phase].into_iter().collect::<VecDeque<i64>>())
+            .collect::<Vec<_>>();
+        queues[0].push_back(0);
+
+        let mut last_signal = None;
+        let mut running = true;
+        while running {
+            running = false;
+            for i in 0..vms.len() {
+                let mut outputs = VecDeque::new();
+                let state = vms[i].run_with(&mut queues[i], &mut outputs).unwrap();
+                running |= state == RunState::AwaitingInput;
+                if i == vms.len() - 1 {
+                    last_signal = outputs.back().copied().or(last_signal);
+                }
+                queues[(i + 1) % phases.len()].extend(outputs);
+            }
+        }
+        assert_eq!(last_signal, Some(139629729));
+    }
+
+    #[test]
+    fn test_run_with_channels() {
+        use std::sync::mpsc::channel;
+        use std::thread;
+
+        let phases = [9, 8, 7, 6, 5];
+        let (senders, mut receivers): (Vec<_>, Vec<_>) = phases
+            .iter()
+            .map(|phase| {
+                let (tx, rx) = channel();
+                tx.send(*phase).unwrap();
+                (tx, Some(rx))
+            })
+            .unzip();
+        senders[0].send(0).unwrap();
+
+        let last_signal = thread::scope(|scope| {
+            let handles = (0..phases.len())
+                .map(|i| {
+                    let mut input = receivers[i].take().unwrap();
+                    let next = senders[(i + 1) % phases.len()].clone();
+                    scope.spawn(move || {
+                        let memory = parse_intcode_program(FEEDBACK_PROGRAM).unwrap();
+                        let mut vm = IntcodeVM::new(memory, None);
+                        let mut last = None;
+                        let mut output = |value| {
+                            last = Some(value);
+                            let _ = next.send(value);
+                        };
+                        vm.run_with(&mut input, &mut output).unwrap();
+                        last
+                    })
+                })
+                .collect::<Vec<_>>();
+            drop(senders);
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .last()
+                .unwrap()
+        });
+        assert_eq!(last_signal, Some(139629729));
+    }
+
+    #[test]
+    fn test_run_with_closures() {
+        let memory = parse_intcode_program("3,0,3,1,1,0,1,2,4,2,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let mut values = vec![20, 22].into_iter();
+        let mut input = || values.next();
+        let mut seen = Vec::new();
+        let state = vm
+            .run_with(&mut input, &mut |value| seen.push(value))
+            .unwrap();
+        assert_eq!(state, RunState::Halted);
+        assert_eq!(seen, [42]);
+
+        let memory = parse_intcode_program("3,0,99").unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let state = vm.run_with(&mut VecDeque::new(), &mut Vec::new()).unwrap();
+        assert_eq!(state, RunState::AwaitingInput);
+    }
+
+    fn run_assembly(source: &str, inputs: &[i64]) -> Vec<i64> {
+        let memory = asm::assemble(source).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        let mut input = inputs.iter().copied().collect::<VecDeque<i64>>();
+        let mut outputs = Vec::new();
+        let state = vm.run_with(&mut input, &mut outputs).unwrap();
+        assert_eq!(state, RunState::Halted);
+        outputs
+    }
+
+    #[test]
+    fn test_opcode_add_mul() {
+        let source = "
+                IN   [a]
+                IN   [b]
+                ADD  [a], [b], [sum]
+                MUL  [a], #-3, [product]
+                OUT  [sum]
+                OUT  [product]
+                HLT
+            a:  .data 0
+            b:  .data 0
+            sum: .data 0
+            product: .data 0
+        ";
+        assert_eq!(run_assembly(source, &[5, 7]), [12, -15]);
+    }
+
+    #[test]
+    fn test_opcode_in_out() {
+        let source = "
+                IN   [x]
+                OUT  [x]
+                OUT  #42
+                HLT
+            x:  .data 0
+        ";
+        assert_eq!(run_assembly(source, &[-9]), [-9, 42]);
+    }
+
+    #[test]
+    fn test_opcode_jumps() {
+        // Counts down from the input, printing each value
+        let source = "
+                IN   [n]
+        loop:   JF   [n], #done
+                OUT  [n]
+                ADD  [n], #-1, [n]
+                JT   #1, #loop
+        done:   HLT
+            n:  .data 0
+        ";
+        assert_eq!(run_assembly(source, &[3]), [3, 2, 1]);
+        assert_eq!(run_assembly(source, &[0]), []);
+    }
+
+    #[test]
+    fn test_opcode_compare() {
+        let source = "
+                IN   [x]
+                LT   [x], #8, [flag]
+                OUT  [flag]
+                EQ   [x], #8, [flag]
+                OUT  [flag]
+                HLT
+            x:  .data 0
+            flag: .data 0
+        ";
+        assert_eq!(run_assembly(source, &[7]), [1, 0]);
+        assert_eq!(run_assembly(source, &[8]), [0, 1]);
+        assert_eq!(run_assembly(source, &[9]), [0, 0]);
+    }
+
+    #[test]
+    fn test_opcode_relative_base() {
+        // Uses rb as a stack pointer into the scratch area past the image
+        let source = "
+                ARB  #100
+                IN   rb
+                IN   rb+1
+                ARB  #1
+                MUL  rb-1, rb, rb+1
+                OUT  rb+1
+                HLT
+        ";
+        assert_eq!(run_assembly(source, &[6, 7]), [42]);
+    }
+
+    #[test]
+    fn test_opcode_halt() {
+        assert_eq!(run_assembly("HLT\nOUT #1", &[]), []);
+    }
+
+    #[test]
+    fn test_decode_cache_self_modifying() {
+        // Runs `print` twice, patching its operand and then its opcode in between
+        let source = "
+        print:  OUT  #1
+                JT   [patched], #halt
+                ADD  #2, #0, [print+1]
+                ADD  #1, #0, [patched]
+                JT   #1, #print
+        halt:   ADD  #99, #0, [print]
+                JT   #1, #print
+        patched: .data 0
+        ";
+        let memory = asm::assemble(source).unwrap();
+        for &enabled in &[true, false] {
+            let mut vm = IntcodeVM::new(memory.clone(), None);
+            vm.set_decode_cache(enabled);
+            assert_eq!(vm.run().unwrap(), [1, 2]);
+            assert!(vm.is_halted());
+        }
+    }
+
+    #[test]
+    fn test_decode_cache_starts_at_backward_jump() {
+        let mut vm = IntcodeVM::new(asm::assemble("OUT #1\nHLT").unwrap(), None);
+        assert_eq!(vm.run().unwrap(), [1]);
+        assert!(vm.decode_cache.is_empty());
+
+        let source = "
+        loop:   ADD  [n], #-1, [n]
+                JT   [n], #loop
+                HLT
+        n:      .data 3
+        ";
+        let mut vm = IntcodeVM::new(asm::assemble(source).unwrap(), None);
+        vm.run().unwrap();
+        assert!(vm.decode_cache.iter().any(Option::is_some));
+        assert!(vm.clone().decode_cache.is_empty());
+    }
+
+    #[test]
+    fn test_decode_cache_external_writes() {
+        let mut vm = IntcodeVM::new(vec![104, 1, 99], None);
+        // there's no backward jump to start the cache, so start it by hand
+        vm.set_decode_cache(true);
+        let start = vm.snapshot();
+        assert_eq!(vm.step().unwrap(), StepResult::Output(1));
+
+        vm.restore(&snapshot::Snapshot {
+            memory: vec![104, 2, 99],
+            ..start.clone()
+        });
+        assert_eq!(vm.step().unwrap(), StepResult::Output(2));
+
+        vm.restore(&start);
+        vm.program_memory[1] = 3;
+        vm.invalidate_decode_cache();
+        assert_eq!(vm.step().unwrap(), StepResult::Output(3));
+
+        vm.restore(&start);
+        vm.poke(1, 4).unwrap();
+        assert_eq!(vm.step().unwrap(), StepResult::Output(4));
+    }
 }
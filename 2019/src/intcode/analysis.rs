@@ -0,0 +1,572 @@
+//! Static analysis of Intcode images: a control-flow graph from the entry point, which
+//! words are code, data or unreachable, and which instructions get overwritten.
+//!
+//! Only jumps with immediate targets can be followed. Jumps through memory are recorded
+//! as `Edge::Indirect`, so code reached only that way shows up as unreachable.
+use super::disasm::decode_line;
+use super::{decode_instruction, Instruction, IntcodeError, OpCode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Range;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Edge {
+    /// Execution continues with the instruction at this address.
+    Next(usize),
+    /// A jump with an immediate target.
+    Jump(usize),
+    /// A jump whose target is only known at runtime.
+    Indirect,
+}
+
+impl Edge {
+    pub fn target(self) -> Option<usize> {
+        match self {
+            Edge::Next(target) | Edge::Jump(target) => Some(target),
+            Edge::Indirect => None,
+        }
+    }
+}
+
+/// Why a reachable address can't be trusted.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Problem {
+    /// The word doesn't decode into an instruction that could execute.
+    Invalid(IntcodeError),
+    /// The instruction's parameters run past the end of the image.
+    Truncated,
+    /// The instruction starts inside the instruction at this address.
+    Overlaps(usize),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Invalid(error) => write!(f, "{}", error),
+            Problem::Truncated => write!(f, "parameters run past the end of the image"),
+            Problem::Overlaps(other) => write!(f, "starts inside the instruction at {}", other),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Region {
+    /// Part of an instruction reachable from the entry point.
+    Code,
+    /// Not code, but read or written by reachable code.
+    Data,
+    Unreachable,
+}
+
+/// A reachable instruction writes into the words of a reachable instruction, or over a
+/// reachable word that doesn't decode yet (which is then likely patched before it runs).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SelfModification {
+    pub writer: usize,
+    pub target: usize,
+    pub instruction: usize,
+}
+
+/// A run of instructions that is only ever entered at its first one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<usize>,
+    pub successors: Vec<Edge>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    memory: Vec<i64>,
+    pub blocks: BTreeMap<usize, Block>,
+    /// Every word of the image, as consecutive runs.
+    pub regions: Vec<(Range<usize>, Region)>,
+    pub self_modifying: Vec<SelfModification>,
+    /// Reachable instructions that write through `rb`, so could modify anything.
+    pub relative_writes: Vec<usize>,
+    pub indirect_jumps: Vec<usize>,
+    pub problems: Vec<(usize, Problem)>,
+}
+
+/// A reachable instruction and where execution can go after it.
+struct Node {
+    instruction: Instruction,
+    successors: Vec<Edge>,
+}
+
+/// Decodes the instruction at `address` and works out its successors.
+fn visit(memory: &[i64], address: usize) -> Result<Node, Problem> {
+    let instruction = decode_instruction(memory, address).map_err(Problem::Invalid)?;
+    if address + instruction.len() > memory.len() {
+        return Err(Problem::Truncated);
+    }
+    let modes = instruction.param_modes();
+    let parameters = instruction.parameters();
+    for (i, (mode, parameter)) in modes.iter().zip(parameters).enumerate() {
+        if instruction.op_code.writes_parameter(i) && *mode == 1 {
+            return Err(Problem::Invalid(IntcodeError::ImmediateModeWrite {
+                address,
+            }));
+        }
+        if *mode == 0 && *parameter < 0 {
+            return Err(Problem::Invalid(IntcodeError::NegativeAddress {
+                target: *parameter,
+                address,
+            }));
+        }
+    }
+
+    let next = Edge::Next(address + instruction.len());
+    let successors = match instruction.op_code {
+        OpCode::Halt => vec![],
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let jump = match (modes[1], parameters[1]) {
+                (1, target) if target < 0 => {
+                    return Err(Problem::Invalid(IntcodeError::NegativeAddress {
+                        target,
+                        address,
+                    }))
+                }
+                (1, target) => Edge::Jump(target as usize),
+                _ => Edge::Indirect,
+            };
+            let jumps_on = instruction.op_code == OpCode::JumpIfTrue;
+            match (modes[0], parameters[0]) {
+                (1, condition) if (condition != 0) == jumps_on => vec![jump],
+                (1, _) => vec![next],
+                _ => vec![jump, next],
+            }
+        }
+        _ => vec![next],
+    };
+    Ok(Node {
+        instruction,
+        successors,
+    })
+}
+
+/// Follows every path from address 0 through `memory`.
+pub fn analyze(memory: &[i64]) -> Analysis {
+    let mut nodes = BTreeMap::new();
+    let mut problems = BTreeMap::new();
+    let mut pending = vec![0];
+    while let Some(address) = pending.pop() {
+        if nodes.contains_key(&address) || problems.contains_key(&address) {
+            continue;
+        }
+        match visit(memory, address) {
+            Ok(node) => {
+                pending.extend(node.successors.iter().filter_map(|edge| edge.target()));
+                nodes.insert(address, node);
+            }
+            Err(problem) => {
+                problems.insert(address, problem);
+            }
+        }
+    }
+
+    // Which instruction each code word belongs to; the first one wins where they overlap
+    let mut owners = BTreeMap::new();
+    for (address, node) in &nodes {
+        for word in *address..address + node.instruction.len() {
+            owners.entry(word).or_insert(*address);
+        }
+    }
+    let invalid = problems.keys().copied().collect::<BTreeSet<_>>();
+    let mut problems = problems.into_iter().collect::<Vec<_>>();
+    for address in nodes.keys() {
+        match owners[address] {
+            owner if owner != *address => problems.push((*address, Problem::Overlaps(owner))),
+            _ => {}
+        }
+    }
+    problems.sort_by_key(|(address, _)| *address);
+
+    let mut referenced = BTreeSet::new();
+    let mut self_modifying = Vec::new();
+    let mut relative_writes = Vec::new();
+    for (address, node) in &nodes {
+        let instruction = &node.instruction;
+        for (i, (mode, parameter)) in instruction
+            .param_modes()
+            .iter()
+            .zip(instruction.parameters())
+            .enumerate()
+        {
+            let writes = instruction.op_code.writes_parameter(i);
+            match mode {
+                0 => {
+                    let target = *parameter as usize;
+                    referenced.insert(target);
+                    let owner = owners
+                        .get(&target)
+                        .copied()
+                        .or_else(|| invalid.get(&target).copied());
+                    match owner {
+                        Some(owner) if writes => self_modifying.push(SelfModification {
+                            writer: *address,
+                            target,
+                            instruction: owner,
+                        }),
+                        _ => {}
+                    }
+                }
+                2 if writes => relative_writes.push(*address),
+                _ => {}
+            }
+        }
+    }
+
+    let mut regions: Vec<(Range<usize>, Region)> = Vec::new();
+    for address in 0..memory.len() {
+        let region = match (owners.contains_key(&address), referenced.contains(&address)) {
+            (true, _) => Region::Code,
+            (false, true) => Region::Data,
+            (false, false) => Region::Unreachable,
+        };
+        match regions.last_mut() {
+            Some((range, last)) if *last == region => range.end += 1,
+            _ => regions.push((address..address + 1, region)),
+        }
+    }
+
+    let indirect_jumps = nodes
+        .iter()
+        .filter(|(_, node)| node.successors.contains(&Edge::Indirect))
+        .map(|(address, _)| *address)
+        .collect();
+
+    Analysis {
+        memory: memory.to_vec(),
+        blocks: build_blocks(&nodes),
+        regions,
+        self_modifying,
+        relative_writes,
+        indirect_jumps,
+        problems,
+    }
+}
+
+/// Splits the reachable instructions into basic blocks. A block starts at the entry
+/// point, at every jump target and after every conditional jump.
+fn build_blocks(nodes: &BTreeMap<usize, Node>) -> BTreeMap<usize, Block> {
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for node in nodes.values() {
+        if let [Edge::Next(_)] = node.successors.as_slice() {
+            continue;
+        }
+        leaders.extend(node.successors.iter().filter_map(|edge| edge.target()));
+    }
+
+    let mut blocks = BTreeMap::new();
+    for start in leaders.iter().filter(|start| nodes.contains_key(start)) {
+        let start = *start;
+        let mut instructions = vec![start];
+        let mut address = start;
+        let successors = loop {
+            let successors = &nodes[&address].successors;
+            match successors.as_slice() {
+                [Edge::Next(next)] if nodes.contains_key(next) && !leaders.contains(next) => {
+                    address = *next;
+                    instructions.push(address);
+                }
+                _ => break successors.clone(),
+            }
+        };
+        blocks.insert(
+            start,
+            Block {
+                start,
+                instructions,
+                successors,
+            },
+        );
+    }
+    blocks
+}
+
+impl Analysis {
+    pub fn region(&self, address: usize) -> Option<Region> {
+        self.regions
+            .iter()
+            .find(|(range, _)| range.contains(&address))
+            .map(|(_, region)| *region)
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.blocks
+            .values()
+            .map(|block| block.instructions.len())
+            .sum()
+    }
+
+    /// The instruction at `address` in assembler syntax, e.g. `ADD [4], #1, [4]`.
+    fn instruction_text(&self, address: usize) -> String {
+        decode_line(&self.memory, address).decoded.to_string()
+    }
+
+    /// The control-flow graph in Graphviz DOT format, one node per basic block. Blocks
+    /// containing instructions that get overwritten are highlighted.
+    pub fn to_dot(&self) -> String {
+        let modified = self
+            .self_modifying
+            .iter()
+            .map(|modification| modification.instruction)
+            .collect::<BTreeSet<_>>();
+        let mut dot = String::new();
+        dot.push_str("digraph intcode {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for address in &block.instructions {
+                let marker = if modified.contains(address) { "*" } else { "" };
+                let text = self.instruction_text(*address);
+                let _ = write!(label, "{}{}: {}\\l", marker, address, text);
+            }
+            let style = match block.instructions.iter().any(|a| modified.contains(a)) {
+                true => ", style=filled, fillcolor=orange",
+                false => "",
+            };
+            let _ = writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, style);
+            for edge in &block.successors {
+                let _ = match edge {
+                    Edge::Next(target) => writeln!(dot, "    b{} -> b{};", block.start, target),
+                    Edge::Jump(target) => {
+                        writeln!(dot, "    b{} -> b{} [label=\"jump\"];", block.start, target)
+                    }
+                    Edge::Indirect => {
+                        writeln!(dot, "    b{} -> indirect [style=dashed];", block.start)
+                    }
+                };
+            }
+        }
+        if !self.indirect_jumps.is_empty() {
+            dot.push_str("    indirect [label=\"?\", shape=circle];\n");
+        }
+        for (address, problem) in &self.problems {
+            if !self.blocks.contains_key(address) {
+                let _ = writeln!(
+                    dot,
+                    "    b{} [label=\"{}: {}\", color=red];",
+                    address, address, problem
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words = |region| {
+            self.regions
+                .iter()
+                .filter(|(_, r)| *r == region)
+                .map(|(range, _)| range.len())
+                .sum::<usize>()
+        };
+        writeln!(
+            f,
+            "{} instructions in {} blocks; {} code, {} data and {} unreachable words",
+            self.instruction_count(),
+            self.blocks.len(),
+            words(Region::Code),
+            words(Region::Data),
+            words(Region::Unreachable)
+        )?;
+
+        writeln!(f, "\nregions:")?;
+        for (range, region) in &self.regions {
+            writeln!(f, "  {:>6}..{:<6} {:?}", range.start, range.end, region)?;
+        }
+
+        if !self.self_modifying.is_empty() {
+            writeln!(f, "\nself-modifying code:")?;
+            for modification in &self.self_modifying {
+                writeln!(
+                    f,
+                    "  {:>6}  {:<24} writes {}, part of {}: {}",
+                    modification.writer,
+                    self.instruction_text(modification.writer),
+                    modification.target,
+                    modification.instruction,
+                    self.instruction_text(modification.instruction)
+                )?;
+            }
+        }
+        if !self.relative_writes.is_empty() {
+            writeln!(f, "\nwrites through rb (targets unknown):")?;
+            for address in &self.relative_writes {
+                writeln!(f, "  {:>6}  {}", address, self.instruction_text(*address))?;
+            }
+        }
+        if !self.indirect_jumps.is_empty() {
+            writeln!(f, "\nindirect jumps (not followed):")?;
+            for address in &self.indirect_jumps {
+                writeln!(f, "  {:>6}  {}", address, self.instruction_text(*address))?;
+            }
+        }
+        if !self.problems.is_empty() {
+            writeln!(f, "\nproblems:")?;
+            for (address, problem) in &self.problems {
+                writeln!(f, "  {:>6}  {}", address, problem)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::parse_intcode_program;
+
+    static JUMP_EQ_8: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                              1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                              999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+    #[test]
+    fn test_control_flow() {
+        let memory = parse_intcode_program(JUMP_EQ_8).unwrap();
+        let analysis = analyze(&memory);
+        assert!(analysis.problems.is_empty());
+        assert!(analysis.self_modifying.is_empty());
+        assert_eq!(
+            analysis.blocks.keys().copied().collect::<Vec<_>>(),
+            [0, 9, 16, 22, 31, 36, 46]
+        );
+        assert_eq!(
+            analysis.blocks[&0].successors,
+            [Edge::Jump(22), Edge::Next(9)]
+        );
+        // `JF #0, #36` always jumps
+        assert_eq!(analysis.blocks[&9].instructions, [9, 13]);
+        assert_eq!(analysis.blocks[&16].successors, [Edge::Jump(36)]);
+        assert!(analysis.blocks[&46].successors.is_empty());
+
+        assert_eq!(
+            analysis.regions,
+            [
+                (0..19, Region::Code),
+                (19..20, Region::Unreachable),
+                (20..22, Region::Data),
+                (22..45, Region::Code),
+                (45..46, Region::Unreachable),
+                (46..47, Region::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_branches() {
+        let memory = assemble(
+            "       JT   #1, #end
+                    .data 12345
+             end:   JF   #1, #12345
+                    HLT",
+        )
+        .unwrap();
+        let analysis = analyze(&memory);
+        assert!(analysis.problems.is_empty());
+        assert_eq!(analysis.region(3), Some(Region::Unreachable));
+        assert_eq!(analysis.instruction_count(), 3);
+    }
+
+    #[test]
+    fn test_self_modification() {
+        let memory = assemble(
+            "       ADD  #1, #0, [out+1]
+             out:   OUT  #0
+                    HLT",
+        )
+        .unwrap();
+        let analysis = analyze(&memory);
+        assert_eq!(
+            analysis.self_modifying,
+            [SelfModification {
+                writer: 0,
+                target: 5,
+                instruction: 4
+            }]
+        );
+        let report = analysis.to_string();
+        assert!(report.contains("writes 5, part of 4: OUT #0"));
+        let dot = analysis.to_dot();
+        assert!(dot.contains("*4: OUT #0\\l"));
+        assert!(dot.contains("fillcolor=orange"));
+    }
+
+    #[test]
+    fn test_problems() {
+        let memory = assemble(
+            "       JF   [x], [x]
+                    ARB  rb+1
+                    ADD  #1, #2, rb+0
+                    JT   [x], #mid+1
+             mid:   ADD  #99, #0, [x]
+             x:     .data 77",
+        )
+        .unwrap();
+        let analysis = analyze(&memory);
+        assert_eq!(analysis.indirect_jumps, [0]);
+        assert_eq!(analysis.relative_writes, [5]);
+        assert_eq!(analysis.blocks[&3].instructions, [3, 5, 9]);
+        // jumping to `mid+1` runs the `#99` operand as a halt
+        assert_eq!(
+            analysis.problems,
+            [
+                (13, Problem::Overlaps(12)),
+                (
+                    16,
+                    Problem::Invalid(IntcodeError::UnknownOpCode {
+                        value: 77,
+                        address: 16
+                    })
+                ),
+            ]
+        );
+        assert!(analysis.to_string().contains("    16  unknown opcode 77"));
+        // `x` is reached by falling off `mid`, which also writes it
+        assert_eq!(
+            analysis.self_modifying,
+            [SelfModification {
+                writer: 12,
+                target: 16,
+                instruction: 16
+            }]
+        );
+
+        let dot = analysis.to_dot();
+        assert!(dot.starts_with("digraph intcode {\n"));
+        assert!(dot.contains("b0 -> indirect [style=dashed];"));
+        assert!(dot.contains("b3 -> b13 [label=\"jump\"];"));
+        assert!(dot.contains("b12 -> b16;"));
+        assert!(dot.contains("b16 [label=\"16: unknown opcode 77 at address 16\", color=red];"));
+    }
+
+    #[test]
+    fn test_patched_before_running() {
+        // the IN overwrites the word it falls through to
+        let analysis = analyze(&[3, 2, 0]);
+        assert_eq!(
+            analysis.self_modifying,
+            [SelfModification {
+                writer: 0,
+                target: 2,
+                instruction: 2
+            }]
+        );
+        assert_eq!(analysis.problems.len(), 1);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let analysis = analyze(&[1, 0, 0]);
+        assert_eq!(analysis.problems, [(0, Problem::Truncated)]);
+        assert!(analysis.blocks.is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! Text I/O for Intcode programs that read lines of ASCII and print characters.
+use super::{IntcodeError, IntcodeVM, RunState};
+
+/// Everything a program printed between two commands.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AsciiOutput {
+    pub text: String,
+    /// The last value printed that isn't an ASCII character, usually the puzzle answer.
+    pub answer: Option<i64>,
+    pub state: RunState,
+}
+
+pub struct AsciiVM {
+    pub vm: IntcodeVM,
+}
+
+impl AsciiVM {
+    pub fn new(vm: IntcodeVM) -> Self {
+        Self { vm }
+    }
+
+    /// Queues `command` as character codes followed by a newline.
+    pub fn send_line(&mut self, command: &str) {
+        for byte in command.bytes() {
+            self.vm.push_input(byte as i64);
+        }
+        self.vm.push_input('\n' as i64);
+    }
+
+    /// Runs until the program halts or waits for the next line.
+    pub fn run(&mut self) -> Result<AsciiOutput, IntcodeError> {
+        let mut outputs = Vec::new();
+        let state = self.vm.run_with(&mut || None, &mut outputs)?;
+        let mut output = AsciiOutput {
+            text: String::new(),
+            answer: None,
+            state,
+        };
+        for value in outputs {
+            match value {
+                0..=127 => output.text.push(value as u8 as char),
+                _ => output.answer = Some(value),
+            }
+        }
+        Ok(output)
+    }
+
+    /// Sends one line and runs until the program wants another.
+    pub fn execute(&mut self, command: &str) -> Result<AsciiOutput, IntcodeError> {
+        self.send_line(command);
+        self.run()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Prints a prompt, echoes one line back, then prints its length and halts.
+    static ECHO: &str = "
+            OUT  #62
+            OUT  #32
+    read:   IN   [c]
+            EQ   [c], #10, [flag]
+            JT   [flag], #done
+            OUT  [c]
+            ADD  [len], #1000, [len]
+            JT   #1, #read
+    done:   OUT  #10
+            OUT  [len]
+            HLT
+        c:  .data 0
+     flag:  .data 0
+      len:  .data 0
+    ";
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let mut ascii = AsciiVM::new(IntcodeVM::new(assemble(ECHO).unwrap(), None));
+        let prompt = ascii.run().unwrap();
+        assert_eq!(prompt.text, "> ");
+        assert_eq!(prompt.answer, None);
+        assert_eq!(prompt.state, RunState::AwaitingInput);
+
+        let output = ascii.execute("north").unwrap();
+        assert_eq!(output.text, "north\n");
+        assert_eq!(output.answer, Some(5000));
+        assert_eq!(output.state, RunState::Halted);
+    }
+}
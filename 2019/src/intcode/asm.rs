@@ -0,0 +1,353 @@
+//! A small assembler for Intcode, using the same operand syntax as the disassembler.
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! loop:   ADD  [counter], #1, [counter]   ; position, immediate and position operands
+//!         LT   [counter], #10, rb+2       ; relative operands are `rb+n` / `rb-n`
+//!         JT   rb+2, #loop                ; labels can be used wherever a number can
+//!         OUT  [counter]
+//!         HLT
+//! counter: .data 0                        ; raw words, comma separated
+//! ```
+use super::{OpCode, OP_CODES};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// A number, a label, or a label plus/minus a number, resolved in the second pass.
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Position(Expr),
+    Immediate(Expr),
+    Relative(Expr),
+}
+
+impl Operand {
+    fn mode(&self) -> i64 {
+        match self {
+            Operand::Position(_) => 0,
+            Operand::Immediate(_) => 1,
+            Operand::Relative(_) => 2,
+        }
+    }
+
+    fn expr(&self) -> &Expr {
+        match self {
+            Operand::Position(expr) | Operand::Immediate(expr) | Operand::Relative(expr) => expr,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(usize, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => 1 + operands.len(),
+            Statement::Data(words) => words.len(),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Result<i64, String> {
+    text.parse::<i64>()
+        .map_err(|_| format!("invalid number '{}'", text))
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("missing value".into());
+    }
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return parse_number(text).map(Expr::Number);
+    }
+    let (label, offset) = match text.find(['+', '-']) {
+        Some(i) => (text[..i].trim(), parse_number(&text[i..].replace(' ', ""))?),
+        None => (text, 0),
+    };
+    match is_label(label) {
+        true => Ok(Expr::Label(label.to_string(), offset)),
+        false => Err(format!("invalid label '{}'", label)),
+    }
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    let text = text.trim();
+    if let Some(value) = text.strip_prefix('#') {
+        Ok(Operand::Immediate(parse_expr(value)?))
+    } else if let Some(address) = text.strip_prefix('[') {
+        match address.strip_suffix(']') {
+            Some(address) => Ok(Operand::Position(parse_expr(address)?)),
+            None => Err(format!("unterminated operand '{}'", text)),
+        }
+    } else if let Some(offset) = text.strip_prefix("rb") {
+        match offset.trim() {
+            "" => Ok(Operand::Relative(Expr::Number(0))),
+            offset if offset.starts_with('+') => Ok(Operand::Relative(parse_expr(&offset[1..])?)),
+            offset if offset.starts_with('-') => match parse_expr(&offset[1..])? {
+                Expr::Number(n) => match n.checked_neg() {
+                    Some(n) => Ok(Operand::Relative(Expr::Number(n))),
+                    None => Err(format!("offset out of range in '{}'", text)),
+                },
+                Expr::Label(..) => Err(format!("can't negate a label in '{}'", text)),
+            },
+            _ => Err(format!("invalid operand '{}'", text)),
+        }
+    } else {
+        Err(format!(
+            "invalid operand '{}' (expected #imm, [pos] or rb+n)",
+            text
+        ))
+    }
+}
+
+fn split_list(text: &str) -> Vec<&str> {
+    match text.trim().is_empty() {
+        true => Vec::new(),
+        false => text.split(',').collect(),
+    }
+}
+
+fn lookup_mnemonic(mnemonic: &str) -> Option<(usize, OpCode, usize)> {
+    OP_CODES
+        .iter()
+        .find(|(_, op_code, _)| op_code.mnemonic().eq_ignore_ascii_case(mnemonic))
+        .copied()
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (head, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text, ""),
+    };
+    if head.eq_ignore_ascii_case(".data") {
+        let words = split_list(rest)
+            .into_iter()
+            .map(parse_expr)
+            .collect::<Result<Vec<_>, _>>()?;
+        if words.is_empty() {
+            return Err(".data needs at least one value".into());
+        }
+        return Ok(Statement::Data(words));
+    }
+
+    let (value, op_code, argument_count) =
+        lookup_mnemonic(head).ok_or_else(|| format!("unknown mnemonic '{}'", head))?;
+    let operands = split_list(rest)
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+    if operands.len() != argument_count {
+        return Err(format!(
+            "{} takes {} operand(s), found {}",
+            op_code.mnemonic(),
+            argument_count,
+            operands.len()
+        ));
+    }
+    for (i, operand) in operands.iter().enumerate() {
+        if op_code.writes_parameter(i) && matches!(operand, Operand::Immediate(_)) {
+            return Err(format!(
+                "operand {} of {} is written to and can't be immediate",
+                i + 1,
+                op_code.mnemonic()
+            ));
+        }
+    }
+    Ok(Statement::Instruction(value, operands))
+}
+
+/// Assembles `source` into a memory image that can be loaded with `IntcodeVM::new`.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblyError> {
+    let mut labels: HashMap<String, i64> = HashMap::new();
+    let mut statements: Vec<(usize, Statement)> = Vec::new();
+    let mut address = 0;
+
+    // First pass: parse statements and assign label addresses
+    for (i, raw_line) in source.lines().enumerate() {
+        let line = i + 1;
+        let error = |message: String| AssemblyError { line, message };
+        let mut text = raw_line.split(';').next().unwrap().trim();
+
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_label(label) {
+                return Err(error(format!("invalid label '{}'", label)));
+            }
+            if labels.insert(label.to_string(), address as i64).is_some() {
+                return Err(error(format!("duplicate label '{}'", label)));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(text).map_err(error)?;
+        address += statement.size();
+        statements.push((line, statement));
+    }
+
+    // Second pass: resolve labels and encode
+    let mut program = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        let resolve = |expr: &Expr| match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Label(label, offset) => labels
+                .get(label)
+                .ok_or_else(|| format!("undefined label '{}'", label))
+                .and_then(|address| {
+                    address
+                        .checked_add(*offset)
+                        .ok_or_else(|| format!("address of '{}{:+}' out of range", label, offset))
+                })
+                .map_err(|message| AssemblyError { line, message }),
+        };
+        match statement {
+            Statement::Data(words) => {
+                for word in &words {
+                    program.push(resolve(word)?);
+                }
+            }
+            Statement::Instruction(value, operands) => {
+                let modes = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, operand)| operand.mode() * 10_i64.pow(i as u32 + 2))
+                    .sum::<i64>();
+                program.push(value as i64 + modes);
+                for operand in &operands {
+                    program.push(resolve(operand.expr())?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parse_intcode_program;
+
+    static JUMP_EQ_8: &str = "
+                IN   [input]
+                EQ   [input], #8, [flag]
+                JT   [flag], #equal
+                LT   #8, [input], [flag]
+                JF   [flag], #below
+                JF   #0, #above
+                .data 98
+        flag:   .data 0
+        input:  .data 0
+        equal:  MUL  [input], #125, [flag]     ; 8 * 125
+                OUT  [flag]
+                JT   #1, #done
+        below:  OUT  #999
+                JT   #1, #done
+        above:  ADD  #1000, #1, [flag]
+                OUT  [flag]
+                JT   #1, #done
+                .data 98
+        done:   HLT
+    ";
+
+    #[test]
+    fn test_assemble_matches_hand_encoding() {
+        let expected = parse_intcode_program(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,\
+             1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,\
+             1105,1,46,98,99",
+        )
+        .unwrap();
+        assert_eq!(assemble(JUMP_EQ_8).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_operands() {
+        let program = assemble(
+            "start: add rb, rb+3, rb-4
+                    mul [end+1], #-7, [start]
+                    arb #end
+             end:   .data 1, start, end-1",
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            [22201, 0, 3, -4, 1002, 11, -7, 0, 109, 10, 1, 0, 9]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| assemble(source).unwrap_err();
+        assert_eq!(
+            error("HLT\nFOO [1]"),
+            AssemblyError {
+                line: 2,
+                message: "unknown mnemonic 'FOO'".into()
+            }
+        );
+        assert_eq!(
+            error("ADD [1], [2]").message,
+            "ADD takes 3 operand(s), found 2"
+        );
+        assert_eq!(error("\n\nJT #1, #nowhere").line, 3);
+        assert_eq!(
+            error("JT #1, #nowhere").message,
+            "undefined label 'nowhere'"
+        );
+        assert_eq!(error("a: HLT\na: HLT").message, "duplicate label 'a'");
+        assert_eq!(
+            error("IN #4").message,
+            "operand 1 of IN is written to and can't be immediate"
+        );
+        assert_eq!(error("OUT #12x").message, "invalid number '12x'");
+        assert_eq!(error("OUT [3").message, "unterminated operand '[3'");
+        assert_eq!(error(".data").message, ".data needs at least one value");
+        assert_eq!(error("1a: HLT").message, "invalid label '1a'");
+        assert_eq!(
+            error("OUT #1\na: OUT #a+9223372036854775807").message,
+            "address of 'a+9223372036854775807' out of range"
+        );
+        assert_eq!(
+            error("OUT rb--9223372036854775808").message,
+            "offset out of range in 'rb--9223372036854775808'"
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//! Compiles an Intcode image into a table of pre-bound closures, for programs that are
+//! run many times over (sweeping noun/verb pairs, searching phase settings, ...).
+//!
+//! Every instruction reachable from address 0 is decoded once, and its operands are
+//! baked into a closure specialised for their modes. Anything the compiled code can't
+//! handle itself (reaching an instruction that has been written over, a jump to an
+//! address that wasn't compiled, running out of input, any error) hands the machine over
+//! to `IntcodeVM`, which picks up at the same instruction, so results are always exactly
+//! the interpreter's.
+use super::{decode_instruction, IntcodeError, IntcodeVM, OpCode, RunState, MAX_MEMORY};
+use std::collections::VecDeque;
+
+/// Machine state while running compiled code.
+struct Machine<'a> {
+    memory: Vec<i64>,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    outputs: Vec<i64>,
+    /// Words baked into a compiled instruction.
+    code: &'a [bool],
+    /// Length of the compiled instruction starting at each address, or 0.
+    lengths: &'a [usize],
+    /// Compiled instructions whose words have been overwritten. Empty until the first
+    /// such write.
+    stale: Vec<bool>,
+}
+
+impl Machine<'_> {
+    fn load(&self, address: usize) -> i64 {
+        *self.memory.get(address).unwrap_or(&0)
+    }
+
+    fn relative(&self, offset: i64) -> Option<usize> {
+        let address = self.relative_base.checked_add(offset)?;
+        match address < 0 {
+            true => None,
+            false => Some(address as usize),
+        }
+    }
+
+    /// Finishes an instruction at `ip` by writing `value` and moving on to `next`.
+    fn store(&mut self, address: usize, value: i64, ip: usize, next: usize) -> Flow {
+        if address >= self.memory.len() {
+            if address >= MAX_MEMORY {
+                return Flow::Interpret(ip);
+            }
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        if let Some(true) = self.code.get(address) {
+            self.mark_stale(address);
+        }
+        Flow::Jump(next)
+    }
+
+    fn mark_stale(&mut self, address: usize) {
+        if self.stale.is_empty() {
+            self.stale.resize(self.lengths.len(), false);
+        }
+        for start in address.saturating_sub(3)..=address {
+            if start + self.lengths[start] > address {
+                self.stale[start] = true;
+            }
+        }
+    }
+
+    fn is_stale(&self, ip: usize) -> bool {
+        matches!(self.stale.get(ip), Some(true))
+    }
+}
+
+enum Flow {
+    Jump(usize),
+    Halt,
+    /// Continue in the interpreter from this address.
+    Interpret(usize),
+}
+
+type Op = Box<dyn Fn(&mut Machine<'_>) -> Flow>;
+
+trait Read: Copy + 'static {
+    fn read(self, machine: &Machine<'_>) -> Option<i64>;
+}
+
+trait Write: Copy + 'static {
+    fn address(self, machine: &Machine<'_>) -> Option<usize>;
+}
+
+#[derive(Clone, Copy)]
+struct Immediate(i64);
+
+#[derive(Clone, Copy)]
+struct Position(usize);
+
+#[derive(Clone, Copy)]
+struct Relative(i64);
+
+/// A parameter whose word may change between runs, so it's read from memory each time.
+#[derive(Clone, Copy)]
+struct Slot {
+    address: usize,
+    mode: u8,
+}
+
+impl Read for Immediate {
+    fn read(self, _: &Machine<'_>) -> Option<i64> {
+        Some(self.0)
+    }
+}
+
+impl Read for Position {
+    fn read(self, machine: &Machine<'_>) -> Option<i64> {
+        Some(machine.load(self.0))
+    }
+}
+
+impl Write for Position {
+    fn address(self, _: &Machine<'_>) -> Option<usize> {
+        Some(self.0)
+    }
+}
+
+impl Read for Relative {
+    fn read(self, machine: &Machine<'_>) -> Option<i64> {
+        Some(machine.load(machine.relative(self.0)?))
+    }
+}
+
+impl Write for Relative {
+    fn address(self, machine: &Machine<'_>) -> Option<usize> {
+        machine.relative(self.0)
+    }
+}
+
+impl Read for Slot {
+    fn read(self, machine: &Machine<'_>) -> Option<i64> {
+        let parameter = machine.load(self.address);
+        match self.mode {
+            1 => Some(parameter),
+            _ => Some(machine.load(self.write_address(machine, parameter)?)),
+        }
+    }
+}
+
+impl Write for Slot {
+    fn address(self, machine: &Machine<'_>) -> Option<usize> {
+        self.write_address(machine, machine.load(self.address))
+    }
+}
+
+impl Slot {
+    fn write_address(self, machine: &Machine<'_>, parameter: i64) -> Option<usize> {
+        match self.mode {
+            0 if parameter >= 0 => Some(parameter as usize),
+            2 => machine.relative(parameter),
+            _ => None,
+        }
+    }
+}
+
+/// How one parameter will be bound.
+#[derive(Clone, Copy)]
+enum Binding {
+    Immediate(i64),
+    Position(usize),
+    Relative(i64),
+    Slot(Slot),
+}
+
+/// Binds `$binding` to a concrete operand type as `$name`, then evaluates `$body`.
+/// Read-only bindings (immediates) make the enclosing function return `None` when
+/// used as a write target.
+macro_rules! read_operand {
+    ($binding:expr, |$name:ident| $body:expr) => {
+        match $binding {
+            Binding::Immediate(value) => {
+                let $name = Immediate(value);
+                $body
+            }
+            Binding::Position(address) => {
+                let $name = Position(address);
+                $body
+            }
+            Binding::Relative(offset) => {
+                let $name = Relative(offset);
+                $body
+            }
+            Binding::Slot(slot) => {
+                let $name = slot;
+                $body
+            }
+        }
+    };
+}
+
+macro_rules! write_operand {
+    ($binding:expr, |$name:ident| $body:expr) => {
+        match $binding {
+            Binding::Immediate(_) => return None,
+            Binding::Position(address) => {
+                let $name = Position(address);
+                $body
+            }
+            Binding::Relative(offset) => {
+                let $name = Relative(offset);
+                $body
+            }
+            Binding::Slot(slot) => {
+                let $name = slot;
+                $body
+            }
+        }
+    };
+}
+
+fn binary<A, B, W, F>(a: A, b: B, target: W, f: F, ip: usize, next: usize) -> Op
+where
+    A: Read,
+    B: Read,
+    W: Write,
+    F: Fn(i64, i64) -> Option<i64> + 'static,
+{
+    Box::new(move |machine| {
+        let result = a
+            .read(machine)
+            .and_then(|a| f(a, b.read(machine)?))
+            .and_then(|value| Some((target.address(machine)?, value)));
+        match result {
+            Some((address, value)) => machine.store(address, value, ip, next),
+            None => Flow::Interpret(ip),
+        }
+    })
+}
+
+fn input<W: Write>(target: W, ip: usize, next: usize) -> Op {
+    Box::new(move |machine| match target.address(machine) {
+        Some(address) if !machine.inputs.is_empty() => {
+            let value = machine.inputs.pop_front().unwrap();
+            machine.store(address, value, ip, next)
+        }
+        _ => Flow::Interpret(ip),
+    })
+}
+
+fn output<A: Read>(a: A, ip: usize, next: usize) -> Op {
+    Box::new(move |machine| match a.read(machine) {
+        Some(value) => {
+            machine.outputs.push(value);
+            Flow::Jump(next)
+        }
+        None => Flow::Interpret(ip),
+    })
+}
+
+fn jump<A: Read, B: Read>(condition: A, target: B, when: bool, ip: usize, next: usize) -> Op {
+    Box::new(move |machine| {
+        let (condition, target) = match (condition.read(machine), target.read(machine)) {
+            (Some(condition), Some(target)) => (condition, target),
+            _ => return Flow::Interpret(ip),
+        };
+        match (condition != 0) == when {
+            true if target < 0 => Flow::Interpret(ip),
+            true => Flow::Jump(target as usize),
+            false => Flow::Jump(next),
+        }
+    })
+}
+
+fn adjust_relative_base<A: Read>(a: A, ip: usize, next: usize) -> Op {
+    Box::new(move |machine| {
+        match a
+            .read(machine)
+            .and_then(|offset| machine.relative_base.checked_add(offset))
+        {
+            Some(relative_base) => {
+                machine.relative_base = relative_base;
+                Flow::Jump(next)
+            }
+            None => Flow::Interpret(ip),
+        }
+    })
+}
+
+fn compile_instruction(op_code: OpCode, params: &[Binding], ip: usize) -> Option<Op> {
+    let next = ip + 1 + params.len();
+    let op = match op_code {
+        OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => {
+            read_operand!(params[0], |a| read_operand!(params[1], |b| {
+                write_operand!(params[2], |c| match op_code {
+                    OpCode::Add => binary(a, b, c, i64::checked_add, ip, next),
+                    OpCode::Multiply => binary(a, b, c, i64::checked_mul, ip, next),
+                    OpCode::LessThan => binary(a, b, c, |a, b| Some((a < b) as i64), ip, next),
+                    _ => binary(a, b, c, |a, b| Some((a == b) as i64), ip, next),
+                })
+            }))
+        }
+        OpCode::Input => write_operand!(params[0], |a| input(a, ip, next)),
+        OpCode::Output => read_operand!(params[0], |a| output(a, ip, next)),
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let when = op_code == OpCode::JumpIfTrue;
+            read_operand!(params[0], |a| read_operand!(params[1], |b| {
+                jump(a, b, when, ip, next)
+            }))
+        }
+        OpCode::AdjustRelativeBase => {
+            read_operand!(params[0], |a| adjust_relative_base(a, ip, next))
+        }
+        OpCode::Halt => Box::new(|_: &mut Machine<'_>| Flow::Halt),
+    };
+    Some(op)
+}
+
+/// The result of running a compiled program.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Execution {
+    pub memory: Vec<i64>,
+    pub outputs: Vec<i64>,
+    pub state: RunState,
+    /// Whether the interpreter had to take over at some point.
+    pub interpreted: bool,
+}
+
+pub struct CompiledProgram {
+    image: Vec<i64>,
+    ops: Vec<Option<Op>>,
+    code: Vec<bool>,
+    lengths: Vec<usize>,
+}
+
+impl CompiledProgram {
+    pub fn compile(image: &[i64]) -> Self {
+        Self::with_slots(image, &[])
+    }
+
+    /// Compiles `image`, leaving the parameters at `slots` to be read at run time so they
+    /// can be patched on each run (day 2's noun and verb, for instance). An instruction
+    /// whose opcode word is a slot isn't compiled.
+    pub fn with_slots(image: &[i64], slots: &[usize]) -> Self {
+        let mut ops = Vec::new();
+        ops.resize_with(image.len(), || None);
+        let mut code = vec![false; image.len()];
+        let mut lengths = vec![0; image.len()];
+
+        let mut pending = vec![0];
+        let mut visited = vec![false; image.len()];
+        while let Some(ip) = pending.pop() {
+            if ip >= image.len() || visited[ip] || slots.contains(&ip) {
+                continue;
+            }
+            visited[ip] = true;
+            let instruction = match decode_instruction(image, ip) {
+                Ok(instruction) if ip + instruction.len() <= image.len() => instruction,
+                _ => continue,
+            };
+
+            let mut params = Vec::with_capacity(instruction.argument_count);
+            for (i, (value, mode)) in instruction
+                .parameters()
+                .iter()
+                .zip(instruction.param_modes())
+                .enumerate()
+            {
+                let address = ip + 1 + i;
+                params.push(match (*mode, *value) {
+                    (mode, _) if slots.contains(&address) => Binding::Slot(Slot { address, mode }),
+                    (0, value) if value >= 0 => Binding::Position(value as usize),
+                    (1, value) => Binding::Immediate(value),
+                    (2, value) => Binding::Relative(value),
+                    // a negative position always fails; leave it to the interpreter
+                    _ => break,
+                });
+            }
+            if params.len() < instruction.argument_count {
+                continue;
+            }
+            let op = match compile_instruction(instruction.op_code, &params, ip) {
+                Some(op) => op,
+                None => continue,
+            };
+            ops[ip] = Some(op);
+            lengths[ip] = instruction.len();
+            code[ip] = true;
+            for (i, param) in params.iter().enumerate() {
+                code[ip + 1 + i] |= !matches!(param, Binding::Slot(_));
+            }
+
+            match (instruction.op_code, params.get(1)) {
+                (OpCode::Halt, _) => {}
+                (OpCode::JumpIfTrue, Some(Binding::Immediate(target)))
+                | (OpCode::JumpIfFalse, Some(Binding::Immediate(target))) => {
+                    pending.push(ip + instruction.len());
+                    if *target >= 0 {
+                        pending.push(*target as usize);
+                    }
+                }
+                _ => pending.push(ip + instruction.len()),
+            }
+        }
+
+        Self {
+            image: image.to_vec(),
+            ops,
+            code,
+            lengths,
+        }
+    }
+
+    /// How many instructions were compiled.
+    pub fn len(&self) -> usize {
+        self.ops.iter().filter(|op| op.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs a fresh copy of the image with `patches` applied, feeding it `inputs`.
+    /// Patched instructions (other than through slots) are left to the interpreter.
+    pub fn run(&self, patches: &[(usize, i64)], inputs: &[i64]) -> Result<Execution, IntcodeError> {
+        let mut machine = Machine {
+            memory: self.image.clone(),
+            relative_base: 0,
+            inputs: inputs.iter().copied().collect(),
+            outputs: Vec::new(),
+            code: &self.code,
+            lengths: &self.lengths,
+            stale: Vec::new(),
+        };
+        for (address, value) in patches {
+            if machine.load(*address) == *value {
+                continue;
+            }
+            if *address >= machine.memory.len() {
+                machine.memory.resize(address + 1, 0);
+            }
+            machine.memory[*address] = *value;
+            if let Some(true) = self.code.get(*address) {
+                machine.mark_stale(*address);
+            }
+        }
+
+        let mut ip = 0;
+        loop {
+            let op = match self.ops.get(ip) {
+                Some(Some(op)) if !machine.is_stale(ip) => op,
+                _ => break,
+            };
+            match op(&mut machine) {
+                Flow::Jump(next) => ip = next,
+                Flow::Interpret(next) => {
+                    ip = next;
+                    break;
+                }
+                Flow::Halt => {
+                    return Ok(Execution {
+                        memory: machine.memory,
+                        outputs: machine.outputs,
+                        state: RunState::Halted,
+                        interpreted: false,
+                    })
+                }
+            }
+        }
+
+        let mut vm = IntcodeVM::new(machine.memory, None);
+        vm.instruction_pointer = ip;
+        vm.relative_base = machine.relative_base;
+        vm.inputs = machine.inputs;
+        let state = vm.run_with(&mut VecDeque::new(), &mut machine.outputs)?;
+        Ok(Execution {
+            memory: vm.program_memory,
+            outputs: machine.outputs,
+            state,
+            interpreted: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{asm::assemble, parse_intcode_program};
+
+    fn interpret(memory: &[i64], inputs: &[i64]) -> Execution {
+        let mut vm = IntcodeVM::new(memory.to_vec(), None);
+        let mut outputs = Vec::new();
+        let mut inputs = inputs.iter().copied().collect::<VecDeque<_>>();
+        let state = vm.run_with(&mut inputs, &mut outputs).unwrap();
+        Execution {
+            memory: vm.program_memory,
+            outputs,
+            state,
+            interpreted: true,
+        }
+    }
+
+    #[test]
+    fn test_compiled_matches_interpreter() {
+        let source = "
+                IN   [n]
+                ARB  #200
+        loop:   JF   [n], #done
+                ADD  rb-90, [n], rb-90
+                MUL  [n], #-1, [neg]
+                LT   [neg], #-2, [flag]
+                OUT  [flag]
+                ADD  [n], #-1, [n]
+                JT   #1, #loop
+        done:   OUT  rb-90
+                EQ   rb-90, #15, [flag]
+                OUT  [flag]
+                HLT
+            n:  .data 0
+          neg:  .data 0
+         flag:  .data 0
+        ";
+        let memory = assemble(source).unwrap();
+        let program = CompiledProgram::compile(&memory);
+        assert_eq!(program.len(), 13);
+        let execution = program.run(&[], &[5]).unwrap();
+        assert_eq!(execution.outputs, [1, 1, 1, 0, 0, 15, 1]);
+        assert!(!execution.interpreted);
+        assert_eq!(
+            execution,
+            Execution {
+                interpreted: false,
+                ..interpret(&memory, &[5])
+            }
+        );
+    }
+
+    #[test]
+    fn test_slots() {
+        let memory = parse_intcode_program("1,0,0,3,2,3,11,0,99,30,40,50").unwrap();
+        let program = CompiledProgram::with_slots(&memory, &[1, 2]);
+        for (noun, verb) in &[(9, 10), (1, 1), (10, 11)] {
+            let execution = program.run(&[(1, *noun), (2, *verb)], &[]).unwrap();
+            let mut patched = memory.clone();
+            patched[1] = *noun;
+            patched[2] = *verb;
+            assert_eq!(execution.memory, interpret(&patched, &[]).memory);
+            assert!(!execution.interpreted);
+        }
+        assert_eq!(
+            program.run(&[(1, 9), (2, 10)], &[]).unwrap().memory[0],
+            3500
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_interpreter() {
+        // the program overwrites the operand of its own output instruction
+        let memory = parse_intcode_program("1101,5,0,5,4,0,99").unwrap();
+        let program = CompiledProgram::compile(&memory);
+        let execution = program.run(&[], &[]).unwrap();
+        assert!(execution.interpreted);
+        assert_eq!(execution.outputs, [5]);
+        assert_eq!(execution, interpret(&memory, &[]));
+
+        // patching compiled code means nothing compiled can be trusted
+        let execution = program.run(&[(1, 6)], &[]).unwrap();
+        assert!(execution.interpreted);
+
+        // running out of input hands over a machine waiting for more
+        let memory = parse_intcode_program("3,9,4,9,3,9,4,9,99,0").unwrap();
+        let execution = CompiledProgram::compile(&memory).run(&[], &[7]).unwrap();
+        assert_eq!(execution.outputs, [7]);
+        assert_eq!(execution.state, RunState::AwaitingInput);
+    }
+
+    #[test]
+    fn test_errors_match_interpreter() {
+        let memory = parse_intcode_program("1,-1,0,0,99").unwrap();
+        assert_eq!(
+            CompiledProgram::compile(&memory).run(&[], &[]),
+            Err(IntcodeError::NegativeAddress {
+                target: -1,
+                address: 0
+            })
+        );
+        let memory = parse_intcode_program("109,-5,22201,0,0,0,99").unwrap();
+        assert_eq!(
+            CompiledProgram::compile(&memory).run(&[], &[]),
+            Err(IntcodeError::NegativeAddress {
+                target: -5,
+                address: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Memory-mapped devices for `IntcodeVM::map_device`.
+//!
+//! A device claims a range of addresses; the program's reads and writes to that range go
+//! to the device instead of memory, so programs talk to it with ordinary instructions.
+//! Instructions are always fetched from plain memory, and `peek`/`poke` bypass devices.
+use super::IntcodeVM;
+use std::cell::RefCell;
+use std::io::Write;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// `offset` is relative to the start of the mapped range. Mapped devices must be `Send` so
+/// the VM can still be moved to another thread.
+pub trait Device {
+    fn read(&mut self, offset: usize) -> i64;
+    fn write(&mut self, offset: usize, value: i64);
+}
+
+impl<D: Device> Device for Arc<Mutex<D>> {
+    fn read(&mut self, offset: usize) -> i64 {
+        self.lock().unwrap().read(offset)
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        self.lock().unwrap().write(offset, value)
+    }
+}
+
+pub(super) struct MappedDevice {
+    range: Range<usize>,
+    // reads go through `&IntcodeVM`, but devices are allowed to change state on a read
+    device: RefCell<Box<dyn Device + Send>>,
+}
+
+impl MappedDevice {
+    pub(super) fn read(&self, address: usize) -> i64 {
+        self.device.borrow_mut().read(address - self.range.start)
+    }
+
+    pub(super) fn write(&self, address: usize, value: i64) {
+        self.device
+            .borrow_mut()
+            .write(address - self.range.start, value)
+    }
+}
+
+impl IntcodeVM {
+    /// Routes the program's reads and writes in `range` to `device`. Later mappings take
+    /// precedence where ranges overlap. Keep a handle to the device with an
+    /// `Arc<Mutex<..>>` to inspect it while the VM is still in use.
+    pub fn map_device(&mut self, range: Range<usize>, device: Box<dyn Device + Send>) {
+        self.devices.push(MappedDevice {
+            range,
+            device: RefCell::new(device),
+        });
+    }
+
+    /// Removes every mapping, leaving plain memory underneath.
+    pub fn unmap_devices(&mut self) {
+        self.devices.clear();
+    }
+
+    pub(super) fn device_at(&self, address: usize) -> Option<&MappedDevice> {
+        self.devices
+            .iter()
+            .rev()
+            .find(|mapped| mapped.range.contains(&address))
+    }
+}
+
+/// Prints every value written to it: printable ASCII and newlines as characters, anything
+/// else as a number on its own line. Reads give zero.
+pub struct Console<W: Write>(pub W);
+
+impl<W: Write> Device for Console<W> {
+    fn read(&mut self, _offset: usize) -> i64 {
+        0
+    }
+
+    fn write(&mut self, _offset: usize, value: i64) {
+        let _ = match value {
+            10 | 32..=126 => write!(self.0, "{}", value as u8 as char),
+            _ => writeln!(self.0, "{}", value),
+        };
+    }
+}
+
+/// A xorshift generator. Every read gives a fresh non-negative number; a write reseeds.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: i64) -> Self {
+        let mut random = Random { state: 0 };
+        random.reseed(seed);
+        random
+    }
+
+    fn reseed(&mut self, seed: i64) {
+        // xorshift never leaves zero, so nudge the seed away from it
+        self.state = (seed as u64) ^ 0x9e37_79b9_7f4a_7c15;
+    }
+}
+
+impl Device for Random {
+    fn read(&mut self, _offset: usize) -> i64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 1) as i64
+    }
+
+    fn write(&mut self, _offset: usize, value: i64) {
+        self.reseed(value);
+    }
+}
+
+/// Reads give the microseconds since the clock was created or last written to.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    start: Instant,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+impl Device for Clock {
+    fn read(&mut self, _offset: usize) -> i64 {
+        self.start.elapsed().as_micros() as i64
+    }
+
+    fn write(&mut self, _offset: usize, _value: i64) {
+        self.start = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use aoc_vm::trace::trace;
+    use aoc_vm::Step;
+
+    /// Records every access so tests can see exactly what reached the device.
+    #[derive(Default)]
+    struct Recorder {
+        reads: Vec<usize>,
+        writes: Vec<(usize, i64)>,
+    }
+
+    impl Device for Recorder {
+        fn read(&mut self, offset: usize) -> i64 {
+            self.reads.push(offset);
+            100 + offset as i64
+        }
+
+        fn write(&mut self, offset: usize, value: i64) {
+            self.writes.push((offset, value));
+        }
+    }
+
+    #[test]
+    fn test_console() {
+        let program = assemble(
+            "ADD #72, #0, [1000]
+             ADD #105, #0, [1000]
+             ADD #10, #0, [1000]
+             MUL #10, #100, [1000]
+             HLT",
+        )
+        .unwrap();
+        let console = Arc::new(Mutex::new(Console(Vec::new())));
+        let mut vm = IntcodeVM::new(program, None);
+        vm.map_device(1000..1001, Box::new(console.clone()));
+        assert_eq!(vm.run().unwrap(), []);
+        assert_eq!(console.lock().unwrap().0, b"Hi\n1000\n");
+        // nothing reached memory, so it never grew to cover the device
+        assert!(vm.program_memory.len() < 1000);
+    }
+
+    #[test]
+    fn test_offsets_and_precedence() {
+        let program = assemble(
+            "OUT  [501]
+             ADD  [502], #0, [503]
+             OUT  [502]
+             HLT",
+        )
+        .unwrap();
+        let outer = Arc::new(Mutex::new(Recorder::default()));
+        let inner = Arc::new(Mutex::new(Recorder::default()));
+        let mut vm = IntcodeVM::new(program, None);
+        vm.map_device(500..510, Box::new(outer.clone()));
+        vm.map_device(502..503, Box::new(inner.clone()));
+        assert_eq!(vm.run().unwrap(), [101, 100]);
+        assert_eq!(outer.lock().unwrap().reads, [1]);
+        assert_eq!(outer.lock().unwrap().writes, [(3, 100)]);
+        assert_eq!(inner.lock().unwrap().reads, [0, 0]);
+        assert!(inner.lock().unwrap().writes.is_empty());
+        assert_eq!(vm.peek(502), 0);
+
+        vm.unmap_devices();
+        vm.poke(502, 7).unwrap();
+        assert_eq!(vm.peek(502), 7);
+    }
+
+    #[test]
+    fn test_random() {
+        let program = assemble(
+            "OUT  [99]
+             OUT  [99]
+             ADD  #1, #0, [99]
+             OUT  [99]
+             HLT",
+        )
+        .unwrap();
+        let outputs = |seed| {
+            let mut vm = IntcodeVM::new(program.clone(), None);
+            vm.map_device(99..100, Box::new(Random::new(seed)));
+            vm.run().unwrap()
+        };
+        let first = outputs(1);
+        assert_eq!(first, outputs(1));
+        assert_ne!(first, outputs(2));
+        assert_ne!(first[0], first[1]);
+        // writing 1 reseeds, so the next value repeats the seed-1 sequence
+        assert_eq!(first[2], first[0]);
+        assert!(first.iter().all(|value| *value >= 0));
+        assert_eq!(outputs(0).len(), 3);
+    }
+
+    #[test]
+    fn test_tracing_reads_devices_once() {
+        let program = assemble(
+            "OUT  [99]
+             ADD  [99], [99], [98]
+             OUT  [98]
+             OUT  [99]
+             HLT",
+        )
+        .unwrap();
+        let vm = || {
+            let mut vm = IntcodeVM::new(program.clone(), None);
+            vm.map_device(99..100, Box::new(Random::new(7)));
+            vm
+        };
+        let untraced = vm().run().unwrap();
+        let (mut traced, mut operands) = (Vec::new(), Vec::new());
+        let stop = trace(&mut vm(), 100, |line| {
+            if let Step::Output(value) = line.step {
+                traced.push(value);
+            }
+            operands.push(line.reads.clone());
+        });
+        assert_eq!(stop, Ok(Step::Halted));
+        assert_eq!(traced, untraced);
+        // the tracer sees the values the instructions actually read
+        let (a, b) = (operands[1][0], operands[1][1]);
+        assert_eq!(operands[0], [traced[0]]);
+        assert_eq!(a + b, traced[1]);
+        assert_eq!(operands[3], [traced[2]]);
+    }
+
+    #[test]
+    fn test_vm_with_devices_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let mut vm = IntcodeVM::new(vec![99], None);
+        vm.map_device(0..1, Box::new(Arc::new(Mutex::new(Clock::new()))));
+        assert_send(&vm);
+        std::thread::spawn(move || vm.run().unwrap())
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_clock() {
+        let mut clock = Clock::new();
+        let first = clock.read(0);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = clock.read(0);
+        assert!(first >= 0 && second >= first + 2000);
+        clock.write(0, 0);
+        assert!(clock.read(0) < second);
+    }
+}
@@ -0,0 +1,272 @@
+//! Linear-sweep disassembler for Intcode memory images.
+use super::{parse_op_code, OpCode};
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    fn new(value: i64, mode: u8) -> Self {
+        match mode {
+            0 => Operand::Position(value),
+            1 => Operand::Immediate(value),
+            _ => Operand::Relative(value),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Position(address) => write!(f, "[{}]", address),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(offset) if *offset < 0 => write!(f, "rb{}", offset),
+            Operand::Relative(offset) => write!(f, "rb+{}", offset),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Decoded {
+    Instruction {
+        mnemonic: &'static str,
+        operands: Vec<Operand>,
+    },
+    Data(i64),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListingLine {
+    pub address: usize,
+    pub words: Vec<i64>,
+    pub decoded: Decoded,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Listing {
+    pub lines: Vec<ListingLine>,
+    /// Instruction addresses that are the immediate target of a jump.
+    pub labels: BTreeSet<usize>,
+}
+
+impl Listing {
+    pub fn label(address: usize) -> String {
+        format!("loc_{}", address)
+    }
+}
+
+/// Decodes the instruction at `address`, or `None` if it isn't a well-formed instruction
+/// that fits inside the image.
+fn decode(memory: &[i64], address: usize) -> Option<(OpCode, Vec<Operand>)> {
+    let (op_code, argument_count, param_modes) =
+        parse_op_code(*memory.get(address)?, address).ok()?;
+    let parameters = memory.get(address + 1..address + 1 + argument_count)?;
+    let operands = parameters
+        .iter()
+        .zip(param_modes.iter())
+        .map(|(value, mode)| Operand::new(*value, *mode))
+        .collect::<Vec<_>>();
+    let immediate_write = operands.iter().enumerate().any(|(i, operand)| {
+        op_code.writes_parameter(i) && matches!(operand, Operand::Immediate(_))
+    });
+    match immediate_write {
+        true => None,
+        false => Some((op_code, operands)),
+    }
+}
+
+/// Assembler syntax without labels, e.g. `ADD [4], #1, [4]` or `DATA 98`.
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Decoded::Data(value) => write!(f, "DATA {}", value),
+            Decoded::Instruction { mnemonic, operands } if operands.is_empty() => {
+                write!(f, "{}", mnemonic)
+            }
+            Decoded::Instruction { mnemonic, operands } => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| operand.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "{} {}", mnemonic, operands.join(", "))
+            }
+        }
+    }
+}
+
+impl ListingLine {
+    /// The immediate target of a `JT`/`JF` instruction, if it has one.
+    pub fn jump_target(&self) -> Option<i64> {
+        match &self.decoded {
+            Decoded::Instruction { mnemonic, operands }
+                if *mnemonic == "JT" || *mnemonic == "JF" =>
+            {
+                match operands.get(1) {
+                    Some(Operand::Immediate(target)) => Some(*target),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn fmt_with_labels(&self, f: &mut fmt::Formatter, labels: &BTreeSet<usize>) -> fmt::Result {
+        let words = self
+            .words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{:>6}  {:<24} ", self.address, words)?;
+        match &self.decoded {
+            Decoded::Data(value) => write!(f, "DATA {}", value),
+            Decoded::Instruction { mnemonic, operands } => {
+                let jump_label = self
+                    .jump_target()
+                    .filter(|target| *target >= 0 && labels.contains(&(*target as usize)));
+                let operands = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, operand)| match jump_label {
+                        Some(target) if i == 1 => format!("#{}", Listing::label(target as usize)),
+                        _ => operand.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match operands.is_empty() {
+                    true => write!(f, "{}", mnemonic),
+                    false => write!(f, "{:<4} {}", mnemonic, operands),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ListingLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_labels(f, &BTreeSet::new())
+    }
+}
+
+/// Decodes the single instruction (or data word) at `address`.
+pub fn decode_line(memory: &[i64], address: usize) -> ListingLine {
+    match decode(memory, address) {
+        Some((op_code, operands)) => ListingLine {
+            address,
+            words: memory[address..=address + operands.len()].to_vec(),
+            decoded: Decoded::Instruction {
+                mnemonic: op_code.mnemonic(),
+                operands,
+            },
+        },
+        None => {
+            let value = *memory.get(address).unwrap_or(&0);
+            ListingLine {
+                address,
+                words: vec![value],
+                decoded: Decoded::Data(value),
+            }
+        }
+    }
+}
+
+/// Walks `memory` from address 0, decoding instructions back to back. Words that don't
+/// decode are emitted as `DATA` and the sweep resumes at the next word.
+pub fn disassemble(memory: &[i64]) -> Listing {
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < memory.len() {
+        let line = decode_line(memory, address);
+        address += line.words.len();
+        lines.push(line);
+    }
+
+    // Only label targets that landed on the start of a decoded instruction
+    let jump_targets = lines
+        .iter()
+        .filter_map(|line| line.jump_target())
+        .collect::<BTreeSet<_>>();
+    let labels = lines
+        .iter()
+        .filter(|line| matches!(line.decoded, Decoded::Instruction { .. }))
+        .map(|line| line.address)
+        .filter(|address| jump_targets.contains(&(*address as i64)))
+        .collect();
+
+    Listing { lines, labels }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if self.labels.contains(&line.address) {
+                writeln!(f, "{}:", Listing::label(line.address))?;
+            }
+            line.fmt_with_labels(f, &self.labels)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parse_intcode_program;
+
+    static JUMP_EQ_8: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                              1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                              999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+    #[test]
+    fn test_disassemble() {
+        let memory = parse_intcode_program(JUMP_EQ_8).unwrap();
+        let listing = disassemble(&memory);
+        assert_eq!(
+            listing.lines[1],
+            ListingLine {
+                address: 2,
+                words: vec![1008, 21, 8, 20],
+                decoded: Decoded::Instruction {
+                    mnemonic: "EQ",
+                    operands: vec![
+                        Operand::Position(21),
+                        Operand::Immediate(8),
+                        Operand::Position(20)
+                    ],
+                },
+            }
+        );
+        assert_eq!(listing.labels, [22, 31, 36, 46].iter().copied().collect());
+
+        let text = listing.to_string();
+        assert!(text.contains("loc_22:\n"));
+        assert!(text.contains("JT   [20], #loc_22\n"));
+        assert!(text.contains("JF   #0, #loc_36\n"));
+        assert!(text.contains("DATA 98\n"));
+        assert!(text.ends_with("HLT\n"));
+    }
+
+    #[test]
+    fn test_operand_modes() {
+        let memory = parse_intcode_program("2201,-3,4,5,1101,1,2").unwrap();
+        let listing = disassemble(&memory);
+        let text = listing.to_string();
+        assert!(text.contains("ADD  rb-3, rb+4, [5]\n"));
+        // the second instruction runs past the end of the image
+        assert_eq!(listing.lines[1].decoded, Decoded::Data(1101));
+        assert_eq!(listing.lines.len(), 4);
+    }
+
+    #[test]
+    fn test_immediate_write_is_data() {
+        let memory = parse_intcode_program("11101,1,1,0,99").unwrap();
+        let listing = disassemble(&memory);
+        assert_eq!(listing.lines[0].decoded, Decoded::Data(11101));
+    }
+}
@@ -0,0 +1,562 @@
+//! Property tests: random programs are run on both `IntcodeVM` and a deliberately naive
+//! reference evaluator, and the two must agree on everything observable.
+//!
+//! Programs come from a small seeded PRNG, so every run checks the same cases and a
+//! failure can be replayed from the seed in its message.
+use super::compile::CompiledProgram;
+use super::{parse_intcode_program, IntcodeVM, RunState, StepResult, MAX_MEMORY};
+use std::collections::VecDeque;
+
+/// xorshift64*
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Uniform in `low..=high`.
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low + 1) as u64) as i64
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Stop {
+    Halted,
+    Starved,
+    Fault,
+    OutOfSteps,
+}
+
+/// Everything a program run can be judged by.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Observed {
+    stop: Stop,
+    outputs: Vec<i64>,
+    memory: Vec<i64>,
+    instruction_pointer: usize,
+    relative_base: i64,
+    pending_inputs: Vec<i64>,
+}
+
+enum Event {
+    Executed,
+    Output(i64),
+    Stop(Stop),
+}
+
+/// Intcode straight from the puzzle text: no decoding tables, no caching, and every
+/// failure collapsed into `Stop::Fault`.
+struct Reference {
+    memory: Vec<i64>,
+    ip: i64,
+    rb: i64,
+    inputs: VecDeque<i64>,
+}
+
+impl Reference {
+    fn load(&self, address: i64) -> Option<i64> {
+        if address < 0 {
+            return None;
+        }
+        Some(*self.memory.get(address as usize).unwrap_or(&0))
+    }
+
+    fn store(&mut self, address: i64, value: i64) -> Option<()> {
+        if address < 0 {
+            return None;
+        }
+        let address = address as usize;
+        if address >= self.memory.len() {
+            if address >= MAX_MEMORY {
+                return None;
+            }
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Some(())
+    }
+
+    fn address(&self, mode: i64, parameter: i64) -> Option<i64> {
+        let address = match mode {
+            0 => parameter,
+            2 => self.rb.checked_add(parameter)?,
+            _ => return None,
+        };
+        if address < 0 {
+            return None;
+        }
+        Some(address)
+    }
+
+    fn value(&self, mode: i64, parameter: i64) -> Option<i64> {
+        match mode {
+            1 => Some(parameter),
+            _ => self.load(self.address(mode, parameter)?),
+        }
+    }
+
+    fn step(&mut self) -> Event {
+        self.try_step().unwrap_or(Event::Stop(Stop::Fault))
+    }
+
+    fn try_step(&mut self) -> Option<Event> {
+        let word = self.load(self.ip)?;
+        if word < 0 {
+            return None;
+        }
+        let argument_count = match word % 100 {
+            1 | 2 | 7 | 8 => 3,
+            5 | 6 => 2,
+            3 | 4 | 9 => 1,
+            99 => 0,
+            _ => return None,
+        };
+        let mut m = [0; 3];
+        let mut p = [0; 3];
+        for i in 0..argument_count {
+            m[i] = word / 10_i64.pow(i as u32 + 2) % 10;
+            if m[i] > 2 {
+                return None;
+            }
+            p[i] = self.load(self.ip + 1 + i as i64)?;
+        }
+
+        let mut event = Event::Executed;
+        match word % 100 {
+            1 => {
+                let sum = self
+                    .value(m[0], p[0])?
+                    .checked_add(self.value(m[1], p[1])?)?;
+                self.store(self.address(m[2], p[2])?, sum)?;
+            }
+            2 => {
+                let product = self
+                    .value(m[0], p[0])?
+                    .checked_mul(self.value(m[1], p[1])?)?;
+                self.store(self.address(m[2], p[2])?, product)?;
+            }
+            3 => {
+                let target = self.address(m[0], p[0])?;
+                match self.inputs.pop_front() {
+                    Some(value) => self.store(target, value)?,
+                    None => return Some(Event::Stop(Stop::Starved)),
+                }
+            }
+            4 => event = Event::Output(self.value(m[0], p[0])?),
+            5 | 6 => {
+                let condition = self.value(m[0], p[0])?;
+                let target = self.value(m[1], p[1])?;
+                if (condition != 0) == (word % 100 == 5) {
+                    if target < 0 {
+                        return None;
+                    }
+                    self.ip = target;
+                    return Some(event);
+                }
+            }
+            7 => {
+                let less = self.value(m[0], p[0])? < self.value(m[1], p[1])?;
+                self.store(self.address(m[2], p[2])?, less as i64)?;
+            }
+            8 => {
+                let equal = self.value(m[0], p[0])? == self.value(m[1], p[1])?;
+                self.store(self.address(m[2], p[2])?, equal as i64)?;
+            }
+            9 => self.rb = self.rb.checked_add(self.value(m[0], p[0])?)?,
+            _ => return Some(Event::Stop(Stop::Halted)),
+        }
+        self.ip += 1 + argument_count as i64;
+        Some(event)
+    }
+}
+
+fn observe_reference(memory: &[i64], inputs: &[i64], budget: usize) -> Observed {
+    let mut reference = Reference {
+        memory: memory.to_vec(),
+        ip: 0,
+        rb: 0,
+        inputs: inputs.iter().copied().collect(),
+    };
+    let mut outputs = Vec::new();
+    let mut stop = Stop::OutOfSteps;
+    for _ in 0..budget {
+        match reference.step() {
+            Event::Executed => {}
+            Event::Output(value) => outputs.push(value),
+            Event::Stop(reason) => {
+                stop = reason;
+                break;
+            }
+        }
+    }
+    Observed {
+        stop,
+        outputs,
+        memory: reference.memory,
+        instruction_pointer: reference.ip as usize,
+        relative_base: reference.rb,
+        pending_inputs: reference.inputs.into_iter().collect(),
+    }
+}
+
+fn observe_vm(vm: &mut IntcodeVM, budget: usize) -> Observed {
+    let mut outputs = Vec::new();
+    let mut stop = Stop::OutOfSteps;
+    for _ in 0..budget {
+        match vm.step() {
+            Ok(StepResult::Executed) => {}
+            Ok(StepResult::Output(value)) => outputs.push(value),
+            Ok(StepResult::AwaitingInput) => {
+                stop = Stop::Starved;
+                break;
+            }
+            Ok(StepResult::Halted) => {
+                stop = Stop::Halted;
+                break;
+            }
+            Err(_) => {
+                stop = Stop::Fault;
+                break;
+            }
+        }
+    }
+    Observed {
+        stop,
+        outputs,
+        memory: vm.program_memory.clone(),
+        instruction_pointer: vm.instruction_pointer(),
+        relative_base: vm.relative_base(),
+        pending_inputs: vm.pending_inputs().iter().copied().collect(),
+    }
+}
+
+fn load_vm(memory: &[i64], inputs: &[i64]) -> IntcodeVM {
+    let mut vm = IntcodeVM::new(memory.to_vec(), None);
+    for input in inputs {
+        vm.push_input(*input);
+    }
+    vm
+}
+
+/// Where generated programs keep their variables, well clear of the code.
+const DATA: i64 = 1000;
+const CELLS: i64 = 16;
+/// The last cell is reserved for loop counters.
+const COUNTER: i64 = DATA + CELLS - 1;
+
+/// Generates programs that only touch their own variables and always terminate:
+/// straight-line arithmetic, comparisons, I/O, relative base moves, forward skips and
+/// counted loops. Loop bodies may also patch immediate operands of earlier
+/// instructions, to catch stale decodes.
+struct Generator {
+    rng: Rng,
+    words: Vec<i64>,
+    relative_base: i64,
+    /// Addresses of immediate operands that can be overwritten without derailing
+    /// the program.
+    patchable: Vec<usize>,
+    /// Inputs needed if every `IN` runs, so the program is never starved.
+    inputs: usize,
+}
+
+impl Generator {
+    fn variable(&mut self) -> i64 {
+        DATA + self.rng.range(0, CELLS - 2)
+    }
+
+    fn read_operand(&mut self) -> (i64, i64) {
+        match self.rng.below(3) {
+            0 => (0, self.variable()),
+            1 => (1, self.rng.range(-50, 50)),
+            _ => (2, self.variable() - self.relative_base),
+        }
+    }
+
+    fn write_operand(&mut self) -> (i64, i64) {
+        match self.rng.chance(50) {
+            true => (0, self.variable()),
+            false => (2, self.variable() - self.relative_base),
+        }
+    }
+
+    fn emit(&mut self, op_code: i64, operands: &[(i64, i64)]) {
+        let modes = operands
+            .iter()
+            .enumerate()
+            .map(|(i, (mode, _))| mode * 10_i64.pow(i as u32 + 2))
+            .sum::<i64>();
+        self.words.push(op_code + modes);
+        self.words.extend(operands.iter().map(|(_, value)| value));
+    }
+
+    fn emit_patchable(&mut self, op_code: i64, operands: &[(i64, i64)]) {
+        let start = self.words.len() + 1;
+        self.emit(op_code, operands);
+        for (i, (mode, _)) in operands.iter().enumerate() {
+            if *mode == 1 {
+                self.patchable.push(start + i);
+            }
+        }
+    }
+
+    fn instruction(&mut self, repeats: usize, in_loop: bool) {
+        match self.rng.below(if in_loop { 9 } else { 10 }) {
+            0 | 1 => {
+                let operands = [
+                    self.read_operand(),
+                    self.read_operand(),
+                    self.write_operand(),
+                ];
+                self.emit_patchable(1, &operands);
+            }
+            2 => {
+                // keep one factor small so loops don't overflow too often
+                let operands = [
+                    (1, self.rng.range(-3, 3)),
+                    self.read_operand(),
+                    self.write_operand(),
+                ];
+                self.emit_patchable(2, &operands);
+            }
+            3 => {
+                let operand = self.write_operand();
+                self.emit(3, &[operand]);
+                self.inputs += repeats;
+            }
+            4 => {
+                let operand = self.read_operand();
+                self.emit_patchable(4, &[operand]);
+            }
+            5 | 6 => {
+                let operands = [
+                    self.read_operand(),
+                    self.read_operand(),
+                    self.write_operand(),
+                ];
+                let op_code = if self.rng.chance(50) { 7 } else { 8 };
+                self.emit_patchable(op_code, &operands);
+            }
+            7 => {
+                // skip forward over a couple of instructions
+                let condition = self.read_operand();
+                let op_code = if self.rng.chance(50) { 5 } else { 6 };
+                self.emit(op_code, &[condition, (1, 0)]);
+                let patch = self.words.len() - 1;
+                for _ in 0..self.rng.range(1, 3) {
+                    self.instruction(repeats, true);
+                }
+                self.words[patch] = self.words.len() as i64;
+            }
+            8 if in_loop && !self.patchable.is_empty() => {
+                let index = self.rng.below(self.patchable.len() as u64) as usize;
+                let target = self.patchable[index] as i64;
+                let value = self.rng.range(-3, 3);
+                self.emit(1, &[(1, value), (1, 0), (0, target)]);
+            }
+            _ => {
+                let offset = self.rng.range(-2000, 2000);
+                self.emit(9, &[(1, offset)]);
+                self.relative_base += offset;
+            }
+        }
+    }
+
+    fn counted_loop(&mut self) {
+        let count = self.rng.range(1, 5);
+        self.emit(1, &[(1, count), (1, 0), (0, COUNTER)]);
+        let start = self.words.len() as i64;
+        for _ in 0..self.rng.range(1, 6) {
+            self.instruction(count as usize, true);
+        }
+        self.emit(1, &[(0, COUNTER), (1, -1), (0, COUNTER)]);
+        self.emit(5, &[(0, COUNTER), (1, start)]);
+    }
+
+    /// A memory image and enough inputs to run it to completion.
+    fn program(seed: u64) -> (Vec<i64>, Vec<i64>) {
+        let mut generator = Generator {
+            rng: Rng::new(seed),
+            words: Vec::new(),
+            relative_base: 0,
+            patchable: Vec::new(),
+            inputs: 0,
+        };
+        for _ in 0..generator.rng.range(1, 4) {
+            match generator.rng.chance(40) {
+                true => generator.counted_loop(),
+                false => {
+                    for _ in 0..generator.rng.range(1, 8) {
+                        generator.instruction(1, false);
+                    }
+                }
+            }
+        }
+        generator.words.push(99);
+
+        let mut memory = generator.words;
+        assert!(memory.len() < DATA as usize, "program overran its data");
+        memory.resize(DATA as usize, 0);
+        let rng = &mut generator.rng;
+        memory.extend((0..CELLS).map(|_| rng.range(-100, 100)));
+        let inputs = (0..generator.inputs)
+            .map(|_| rng.range(-100, 100))
+            .collect();
+        (memory, inputs)
+    }
+}
+
+/// Mostly plausible instructions with small operands, mixed with junk: bad modes, unknown
+/// opcodes, negative and far-away addresses.
+fn arbitrary_program(rng: &mut Rng) -> (Vec<i64>, Vec<i64>) {
+    let length = rng.range(1, 40);
+    let memory = (0..length)
+        .map(|_| match rng.below(10) {
+            0..=3 => {
+                let op_code = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99][rng.below(10) as usize];
+                op_code + 100 * rng.range(0, 2) + 1000 * rng.range(0, 2) + 10000 * rng.range(0, 3)
+            }
+            4..=7 => rng.range(-3, length + 3),
+            8 => rng.range(-100_000, 100_000),
+            _ => match rng.below(3) {
+                0 => i64::MAX - rng.range(0, 10),
+                1 => i64::MIN + rng.range(0, 10),
+                _ => MAX_MEMORY as i64 + rng.range(0, 1),
+            },
+        })
+        .collect();
+    let inputs = (0..rng.range(0, 4)).map(|_| rng.range(-10, 10)).collect();
+    (memory, inputs)
+}
+
+const BUDGET: usize = 10_000;
+
+#[test]
+fn test_generated_programs_match_reference() {
+    let mut halted = 0;
+    for seed in 0..500 {
+        let (memory, inputs) = Generator::program(seed);
+        let expected = observe_reference(&memory, &inputs, BUDGET);
+        let actual = observe_vm(&mut load_vm(&memory, &inputs), BUDGET);
+        assert_eq!(actual, expected, "seed {}", seed);
+        assert_ne!(expected.stop, Stop::Starved, "seed {}", seed);
+        if expected.stop == Stop::Halted {
+            halted += 1;
+        }
+    }
+    // the only way out short of halting is an overflow
+    assert!(halted > 450, "only {} of 500 programs halted", halted);
+}
+
+#[test]
+fn test_arbitrary_programs_match_reference() {
+    let mut rng = Rng::new(2019);
+    for case in 0..3000 {
+        let (memory, inputs) = arbitrary_program(&mut rng);
+        let expected = observe_reference(&memory, &inputs, 500);
+        let mut vm = load_vm(&memory, &inputs);
+        assert_eq!(
+            observe_vm(&mut vm, 500),
+            expected,
+            "case {}: {:?}",
+            case,
+            memory
+        );
+
+        let mut uncached = load_vm(&memory, &inputs);
+        uncached.set_decode_cache(false);
+        assert_eq!(
+            observe_vm(&mut uncached, 500),
+            expected,
+            "case {}: {:?}",
+            case,
+            memory
+        );
+    }
+}
+
+#[test]
+fn test_compiled_programs_match_reference() {
+    let mut rng = Rng::new(15);
+    for seed in 0..1000 {
+        let (memory, inputs) = match seed % 2 {
+            0 => Generator::program(seed),
+            _ => arbitrary_program(&mut rng),
+        };
+        let expected = observe_reference(&memory, &inputs, BUDGET);
+        // compiled code has no step budget
+        if expected.stop == Stop::OutOfSteps {
+            continue;
+        }
+        let program = CompiledProgram::compile(&memory);
+        match program.run(&[], &inputs) {
+            Ok(execution) => {
+                let stop = match execution.state {
+                    RunState::Halted => Stop::Halted,
+                    RunState::AwaitingInput => Stop::Starved,
+                };
+                assert_eq!(stop, expected.stop, "seed {}", seed);
+                assert_eq!(execution.outputs, expected.outputs, "seed {}", seed);
+                assert_eq!(execution.memory, expected.memory, "seed {}", seed);
+            }
+            Err(_) => assert_eq!(expected.stop, Stop::Fault, "seed {}", seed),
+        }
+    }
+}
+
+#[test]
+fn test_clones_are_deterministic() {
+    let mut rng = Rng::new(7);
+    for seed in 0..200 {
+        let (memory, inputs) = match seed % 2 {
+            0 => Generator::program(seed),
+            _ => arbitrary_program(&mut rng),
+        };
+        let mut original = load_vm(&memory, &inputs);
+        let start = original.snapshot();
+        let mut copy = original.clone();
+        let expected = observe_vm(&mut original, BUDGET);
+        assert_eq!(observe_vm(&mut copy, BUDGET), expected, "seed {}", seed);
+
+        // and again from a clone taken halfway through
+        let mut halfway = load_vm(&memory, &inputs);
+        observe_vm(&mut halfway, 3);
+        let mut resumed = halfway.clone();
+        let rest = observe_vm(&mut halfway, BUDGET);
+        assert_eq!(observe_vm(&mut resumed, BUDGET), rest, "seed {}", seed);
+
+        original.restore(&start);
+        assert_eq!(observe_vm(&mut original, BUDGET), expected, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_arbitrary_text_never_panics() {
+    let alphabet = b"0123456789,-+ \n\tx";
+    let mut rng = Rng::new(42);
+    for _ in 0..2000 {
+        let text = (0..rng.range(0, 30))
+            .map(|_| alphabet[rng.below(alphabet.len() as u64) as usize] as char)
+            .collect::<String>();
+        if let Ok(memory) = parse_intcode_program(&text) {
+            let mut vm = IntcodeVM::new(memory, None);
+            observe_vm(&mut vm, 100);
+        }
+    }
+}
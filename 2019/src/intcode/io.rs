@@ -0,0 +1,58 @@
+//! Pluggable input sources and output sinks for `IntcodeVM::run_with`.
+//!
+//! Sources return `None` when they have nothing to offer right now, which pauses the
+//! machine with `RunState::AwaitingInput` so a scheduler can run something else.
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, Sender};
+
+pub trait InputSource {
+    fn next_input(&mut self) -> Option<i64>;
+}
+
+pub trait OutputSink {
+    fn send_output(&mut self, value: i64);
+}
+
+impl InputSource for VecDeque<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+/// Blocks until a value arrives. Returns `None` once every sender has been dropped.
+impl InputSource for Receiver<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn next_input(&mut self) -> Option<i64> {
+        self()
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn send_output(&mut self, value: i64) {
+        self.push_back(value);
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn send_output(&mut self, value: i64) {
+        self.push(value);
+    }
+}
+
+/// Values sent after the receiving end has hung up are dropped.
+impl OutputSink for Sender<i64> {
+    fn send_output(&mut self, value: i64) {
+        let _ = self.send(value);
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for F {
+    fn send_output(&mut self, value: i64) {
+        self(value)
+    }
+}
@@ -0,0 +1,201 @@
+//! An undo journal for `IntcodeVM`, so executed instructions can be stepped backwards.
+//!
+//! Each recorded instruction keeps the ip and relative base it started with, the memory
+//! cell it overwrote and the input it consumed. Outputs already sent and writes to
+//! mapped devices can't be taken back.
+use super::IntcodeVM;
+use aoc_vm::{MemoryWrite, Undone};
+use std::collections::VecDeque;
+
+/// Enough to undo one executed instruction.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Entry {
+    pub(super) ip: usize,
+    pub(super) relative_base: i64,
+    pub(super) write: Option<MemoryWrite>,
+    pub(super) input: Option<i64>,
+    /// Memory length before the instruction, if its write grew memory.
+    pub(super) memory_len: Option<usize>,
+}
+
+/// The most recent `capacity` entries; older ones are forgotten.
+#[derive(Debug, Clone)]
+pub(super) struct Journal {
+    entries: VecDeque<Entry>,
+    capacity: usize,
+}
+
+impl Journal {
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(super) fn push(&mut self, entry: Entry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        if self.capacity > 0 {
+            self.entries.push_back(entry);
+        }
+    }
+}
+
+impl IntcodeVM {
+    /// Journals every instruction executed from now on, keeping the last `capacity` of
+    /// them for `reverse_step`. Restarting drops what was recorded so far.
+    pub fn start_recording(&mut self, capacity: usize) {
+        self.journal = Some(Journal {
+            entries: VecDeque::with_capacity(capacity.min(1 << 16)),
+            capacity,
+        });
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.journal = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// How many instructions can currently be undone.
+    pub fn recorded_steps(&self) -> usize {
+        self.journal
+            .as_ref()
+            .map_or(0, |journal| journal.entries.len())
+    }
+
+    /// Undoes the most recently executed instruction, or returns `None` once the journal
+    /// is exhausted (or isn't recording).
+    pub fn reverse_step(&mut self) -> Option<Undone> {
+        let entry = self.journal.as_mut()?.entries.pop_back()?;
+        if let Some(write) = entry.write {
+            self.program_memory[write.address] = write.old;
+            if !self.decode_cache.is_empty() {
+                self.invalidate_decoded(write.address);
+            }
+        }
+        if let Some(len) = entry.memory_len {
+            self.program_memory.truncate(len);
+            self.decode_cache.truncate(len);
+        }
+        if let Some(value) = entry.input {
+            self.inputs.push_front(value);
+        }
+        self.instruction_pointer = entry.ip;
+        self.relative_base = entry.relative_base;
+        Some(Undone {
+            ip: entry.ip,
+            write: entry.write,
+        })
+    }
+
+    /// Steps backwards until the instruction that last wrote `address` has been undone,
+    /// leaving it as the next one to execute. Returns that write, or `None` if the
+    /// journal ran out first.
+    pub fn reverse_to_write(&mut self, address: usize) -> Option<MemoryWrite> {
+        loop {
+            match self.reverse_step()?.write {
+                Some(write) if write.address == address => return Some(write),
+                _ => {}
+            }
+        }
+    }
+
+    pub(super) fn journal_entry(&mut self, entry: Entry) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{RunState, StepResult};
+
+    static DOUBLE_INPUTS: &str = "
+        loop:   IN   [x]
+                MUL  [x], #2, [x]
+                OUT  [x]
+                ARB  #1
+                ADD  [x], #0, rb+100
+                JT   #1, #loop
+            x:  .data 0
+    ";
+
+    #[test]
+    fn test_reverse_step_restores_state() {
+        let memory = assemble(DOUBLE_INPUTS).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.start_recording(1000);
+        vm.push_input(3);
+        vm.push_input(5);
+
+        let mut states = vec![vm.snapshot()];
+        while vm.step().unwrap() != StepResult::AwaitingInput {
+            states.push(vm.snapshot());
+        }
+        assert_eq!(vm.recorded_steps(), 12);
+        // the relative writes grew memory past the image
+        assert_eq!(vm.peek(101), 6);
+
+        while let Some(undone) = vm.reverse_step() {
+            states.pop();
+            assert_eq!(vm.snapshot(), *states.last().unwrap());
+            assert_eq!(undone.ip, vm.instruction_pointer());
+        }
+        assert_eq!(states.len(), 1);
+
+        // replaying from the start gives the same outputs again
+        let mut outputs = Vec::new();
+        let state = vm.run_with(&mut VecDeque::new(), &mut outputs);
+        assert_eq!(state, Ok(RunState::AwaitingInput));
+        assert_eq!(outputs, [6, 10]);
+    }
+
+    #[test]
+    fn test_reverse_to_write() {
+        let memory = assemble(DOUBLE_INPUTS).unwrap();
+        let x = memory.len() - 1;
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.start_recording(1000);
+        vm.push_input(3);
+        vm.push_input(5);
+        while vm.step().unwrap() != StepResult::AwaitingInput {}
+
+        let write = vm.reverse_to_write(x).unwrap();
+        assert_eq!((write.old, write.new), (5, 10));
+        assert_eq!(vm.instruction_pointer(), 2);
+        let write = vm.reverse_to_write(x).unwrap();
+        assert_eq!((write.old, write.new), (6, 5));
+        assert_eq!(vm.instruction_pointer(), 0);
+        assert_eq!(vm.pending_inputs(), &[5]);
+        assert_eq!(vm.reverse_to_write(x).map(|write| write.new), Some(6));
+        assert_eq!(vm.reverse_to_write(x).map(|write| write.new), Some(3));
+        assert_eq!(vm.reverse_to_write(x), None);
+        assert_eq!(vm.pending_inputs(), &[3, 5]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let memory = assemble(DOUBLE_INPUTS).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.start_recording(4);
+        vm.push_input(3);
+        while vm.step().unwrap() != StepResult::AwaitingInput {}
+        assert_eq!(vm.recorded_steps(), 4);
+        for _ in 0..4 {
+            assert!(vm.reverse_step().is_some());
+        }
+        assert_eq!(vm.reverse_step(), None);
+        // the first two instructions are out of reach
+        assert_eq!(vm.instruction_pointer(), 6);
+
+        vm.stop_recording();
+        assert!(!vm.is_recording());
+        vm.step().unwrap();
+        assert_eq!(vm.recorded_steps(), 0);
+    }
+}
@@ -0,0 +1,303 @@
+//! The interpreter loop as it was before instructions were decoded into `Instruction`s:
+//! every step formats the opcode as a string to pick out its digits, looks it up in a
+//! `HashMap` and collects the modes and parameters into `Vec`s. Kept only as the baseline
+//! for `intcode-bench`, apart from `IntcodeVM` so it costs the VM nothing.
+use super::{IntcodeError, OpCode, MAX_MEMORY, OP_CODES};
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+/// Runs `memory` to completion, reading from `inputs`, and returns its outputs.
+pub fn run(memory: Vec<i64>, inputs: &[i64]) -> Result<Vec<i64>, IntcodeError> {
+    let mut machine = Machine {
+        program_memory: memory,
+        instruction_pointer: 0,
+        relative_base: 0,
+        inputs: inputs.iter().copied().collect(),
+    };
+    let mut outputs = Vec::new();
+    loop {
+        match machine.step()? {
+            Step::Executed => {}
+            Step::Output(value) => outputs.push(value),
+            Step::Halted => return Ok(outputs),
+        }
+    }
+}
+
+enum Step {
+    Executed,
+    Output(i64),
+    Halted,
+}
+
+struct Machine {
+    program_memory: Vec<i64>,
+    instruction_pointer: usize,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+}
+
+impl Machine {
+    fn step(&mut self) -> Result<Step, IntcodeError> {
+        let ip = self.instruction_pointer;
+        let (op_code, param_modes) = parse_op_code(self.read(ip), ip)?;
+        let parameters = get_parameters(ip, param_modes.len(), &self.program_memory);
+        self.execute(op_code, &param_modes, &parameters)
+    }
+
+    fn execute(
+        &mut self,
+        op_code: OpCode,
+        param_modes: &[usize],
+        parameters: &[i64],
+    ) -> Result<Step, IntcodeError> {
+        let argument_count = param_modes.len();
+        let mut result = Step::Executed;
+        match op_code {
+            OpCode::Halt => return Ok(Step::Halted),
+            OpCode::Add => {
+                let operand_1 = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let operand_2 = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let dest_pointer = self.resolve_address(parameters[2], param_modes[2])?;
+                let sum = operand_1.checked_add(operand_2).ok_or(self.overflow())?;
+                self.write(dest_pointer, sum)?;
+            }
+            OpCode::Multiply => {
+                let operand_1 = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let operand_2 = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let dest_pointer = self.resolve_address(parameters[2], param_modes[2])?;
+                let product = operand_1.checked_mul(operand_2).ok_or(self.overflow())?;
+                self.write(dest_pointer, product)?;
+            }
+            OpCode::Input => {
+                let target = self.resolve_address(parameters[0], param_modes[0])?;
+                match self.inputs.pop_front() {
+                    Some(val) => {
+                        self.write(target, val)?;
+                    }
+                    None => {
+                        return Err(IntcodeError::InputStarvation {
+                            address: self.instruction_pointer,
+                        })
+                    }
+                }
+            }
+            OpCode::Output => {
+                let output = self.resolve_parameter(parameters[0], param_modes[0])?;
+                result = Step::Output(output);
+            }
+            OpCode::JumpIfTrue => {
+                let condition = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let target = self.resolve_parameter(parameters[1], param_modes[1])?;
+                if condition != 0 {
+                    self.instruction_pointer = self.checked_address(target)?;
+                    return Ok(result);
+                }
+            }
+            OpCode::JumpIfFalse => {
+                let condition = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let target = self.resolve_parameter(parameters[1], param_modes[1])?;
+                if condition == 0 {
+                    self.instruction_pointer = self.checked_address(target)?;
+                    return Ok(result);
+                }
+            }
+            OpCode::LessThan => {
+                let first = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let second = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let target = self.resolve_address(parameters[2], param_modes[2])?;
+
+                match first < second {
+                    true => self.write(target, 1)?,
+                    false => self.write(target, 0)?,
+                }
+            }
+            OpCode::Equals => {
+                let first = self.resolve_parameter(parameters[0], param_modes[0])?;
+                let second = self.resolve_parameter(parameters[1], param_modes[1])?;
+                let target = self.resolve_address(parameters[2], param_modes[2])?;
+
+                match first == second {
+                    true => self.write(target, 1)?,
+                    false => self.write(target, 0)?,
+                }
+            }
+            OpCode::AdjustRelativeBase => {
+                let offset = self.resolve_parameter(parameters[0], param_modes[0])?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or(self.overflow())?;
+            }
+        }
+
+        self.instruction_pointer += 1 + argument_count;
+        Ok(result)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        *self.program_memory.get(address).unwrap_or(&0)
+    }
+
+    fn write(&mut self, address: usize, value: i64) -> Result<(), IntcodeError> {
+        if address >= self.program_memory.len() {
+            if address >= MAX_MEMORY {
+                return Err(IntcodeError::AddressOutOfRange {
+                    target: address as i64,
+                    address: self.instruction_pointer,
+                });
+            }
+            self.program_memory.resize(address + 1, 0);
+        }
+        self.program_memory[address] = value;
+        Ok(())
+    }
+
+    fn resolve_parameter(&self, param_value: i64, param_mode: usize) -> Result<i64, IntcodeError> {
+        match param_mode {
+            1 => Ok(param_value),
+            _ => Ok(self.read(self.resolve_address(param_value, param_mode)?)),
+        }
+    }
+
+    fn resolve_address(&self, param_value: i64, param_mode: usize) -> Result<usize, IntcodeError> {
+        let address = match param_mode {
+            0 => param_value,
+            2 => self
+                .relative_base
+                .checked_add(param_value)
+                .ok_or(self.overflow())?,
+            _ => {
+                return Err(IntcodeError::ImmediateModeWrite {
+                    address: self.instruction_pointer,
+                })
+            }
+        };
+        self.checked_address(address)
+    }
+
+    fn checked_address(&self, target: i64) -> Result<usize, IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                target,
+                address: self.instruction_pointer,
+            });
+        }
+        Ok(target as usize)
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::ArithmeticOverflow {
+            address: self.instruction_pointer,
+        }
+    }
+}
+
+fn op_codes() -> &'static HashMap<usize, (OpCode, usize)> {
+    static OP_CODE_MAP: OnceLock<HashMap<usize, (OpCode, usize)>> = OnceLock::new();
+    OP_CODE_MAP.get_or_init(|| {
+        OP_CODES
+            .iter()
+            .map(|&(value, op_code, argument_count)| (value, (op_code, argument_count)))
+            .collect()
+    })
+}
+
+fn get_parameters(instruction_pointer: usize, count: usize, program_memory: &[i64]) -> Vec<i64> {
+    let mut results = Vec::new();
+    for param_i in 1..=count {
+        let param_value = *program_memory
+            .get(instruction_pointer + param_i)
+            .unwrap_or(&0);
+        results.push(param_value);
+    }
+    results
+}
+
+fn build_parameter_modes(
+    num_parameters: usize,
+    param_mode_data: Vec<usize>,
+    address: usize,
+) -> Result<Vec<usize>, IntcodeError> {
+    let mut modes = Vec::new();
+    for i in 0..num_parameters {
+        let mode = *param_mode_data.get(i).unwrap_or(&0);
+        if mode > 2 {
+            return Err(IntcodeError::InvalidParameterMode {
+                mode: mode as i64,
+                address,
+            });
+        }
+        modes.push(mode);
+    }
+    Ok(modes)
+}
+
+fn parse_op_code(input: i64, address: usize) -> Result<(OpCode, Vec<usize>), IntcodeError> {
+    let unknown = IntcodeError::UnknownOpCode {
+        value: input,
+        address,
+    };
+    if input < 0 {
+        return Err(unknown);
+    }
+    let mut digits_reverse = input
+        .to_string()
+        .chars()
+        .map(|d| d.to_digit(10).unwrap() as usize)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev();
+    let ones = digits_reverse.next().unwrap();
+    let tens = digits_reverse.next().unwrap_or(0);
+    let param_mode_data = digits_reverse.collect::<Vec<usize>>();
+
+    let op_code_value = tens * 10 + ones;
+    let (op_code, argument_count) = op_codes().get(&op_code_value).ok_or(unknown)?;
+    Ok((
+        *op_code,
+        build_parameter_modes(*argument_count, param_mode_data, address)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{asm, IntcodeVM};
+
+    #[test]
+    fn test_matches_vm() {
+        let source = "
+                IN   [n]
+        loop:   JF   [n], #done
+                MUL  [n], [n], [square]
+                ADD  [sum], [square], [sum]
+                ADD  [n], #-1, [n]
+                JT   #1, #loop
+        done:   OUT  [sum]
+                HLT
+            n:  .data 0
+          sum:  .data 0
+       square:  .data 0
+        ";
+        let memory = asm::assemble(source).unwrap();
+        let mut vm = IntcodeVM::new(memory.clone(), None);
+        vm.push_input(10);
+        assert_eq!(run(memory.clone(), &[10]), vm.run());
+        assert_eq!(run(memory, &[10]), Ok(vec![385]));
+
+        // relative mode
+        assert_eq!(run(vec![109, 6, 204, -1, 99, 42], &[]), Ok(vec![42]));
+        assert_eq!(
+            run(vec![3, 0, 99], &[]),
+            Err(IntcodeError::InputStarvation { address: 0 })
+        );
+        assert_eq!(
+            run(vec![42], &[]),
+            Err(IntcodeError::UnknownOpCode {
+                value: 42,
+                address: 0
+            })
+        );
+    }
+}
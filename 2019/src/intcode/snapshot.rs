@@ -0,0 +1,207 @@
+//! Saving and restoring the complete state of an `IntcodeVM`.
+//!
+//! Snapshots serialize to a small line-based text format so long runs can be
+//! written to disk and resumed later:
+//!
+//! ```text
+//! ip 12
+//! rb 0
+//! inputs 5,7
+//! memory 1002,4,3,4,33
+//! ```
+use super::IntcodeVM;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Snapshot {
+    pub memory: Vec<i64>,
+    pub instruction_pointer: usize,
+    pub relative_base: i64,
+    pub inputs: VecDeque<i64>,
+}
+
+impl IntcodeVM {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.program_memory.clone(),
+            instruction_pointer: self.instruction_pointer,
+            relative_base: self.relative_base,
+            inputs: self.inputs.clone(),
+        }
+    }
+
+    /// Rewinds (or fast-forwards) the machine to `snapshot`. The input handler and tracing
+    /// are left as they are; a recording journal starts over.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.program_memory = snapshot.memory.clone();
+        self.instruction_pointer = snapshot.instruction_pointer;
+        self.relative_base = snapshot.relative_base;
+        self.inputs = snapshot.inputs.clone();
+        self.invalidate_decode_cache();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.clear();
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        let mut vm = IntcodeVM::new(Vec::new(), None);
+        vm.restore(snapshot);
+        vm
+    }
+}
+
+/// Clones the machine state and input handler. Tracing, devices, the undo journal and
+/// decoded instructions are not cloned, so the copy starts out untraced and unrecorded
+/// with plain memory throughout, and its decode cache starts filling at its own first
+/// backward jump.
+impl Clone for IntcodeVM {
+    fn clone(&self) -> Self {
+        let mut vm = IntcodeVM::from_snapshot(&self.snapshot());
+        vm.handle_input = self.handle_input;
+        vm.decode_cache_enabled = self.decode_cache_enabled;
+        vm
+    }
+}
+
+impl Snapshot {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+}
+
+fn join(values: impl Iterator<Item = i64>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "ip {}", self.instruction_pointer)?;
+        writeln!(f, "rb {}", self.relative_base)?;
+        writeln!(f, "inputs {}", join(self.inputs.iter().copied()))?;
+        writeln!(f, "memory {}", join(self.memory.iter().copied()))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut instruction_pointer = None;
+        let mut relative_base = None;
+        let mut inputs = None;
+        let mut memory = None;
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.trim().splitn(2, ' ');
+            let key = parts.next().unwrap();
+            let value = parts.next().unwrap_or("").trim();
+            let list = || {
+                value
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.trim().parse::<i64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("bad {} value: {}", key, e))
+            };
+            match key {
+                "ip" => {
+                    instruction_pointer = Some(value.parse().map_err(|e| format!("bad ip: {}", e))?)
+                }
+                "rb" => relative_base = Some(value.parse().map_err(|e| format!("bad rb: {}", e))?),
+                "inputs" => inputs = Some(list()?.into_iter().collect()),
+                "memory" => memory = Some(list()?),
+                _ => return Err(format!("unknown snapshot field '{}'", key)),
+            }
+        }
+
+        Ok(Snapshot {
+            memory: memory.ok_or("snapshot is missing memory")?,
+            instruction_pointer: instruction_pointer.ok_or("snapshot is missing ip")?,
+            relative_base: relative_base.ok_or("snapshot is missing rb")?,
+            inputs: inputs.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // Reads a direction, then prints whether it was "the right one"
+    static BRANCHY: &str = "
+                ARB  #100
+                IN   rb
+                EQ   rb, #3, rb+1
+                OUT  rb+1
+                HLT
+    ";
+
+    #[test]
+    fn test_clone_explores_branches() {
+        let mut vm = IntcodeVM::new(assemble(BRANCHY).unwrap(), None);
+        assert_eq!(vm.next(), Some(Ok(None)));
+
+        let outcomes = (1..=4)
+            .map(|direction| {
+                let mut branch = vm.clone();
+                branch.push_input(direction);
+                branch.run().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outcomes, [[0], [0], [1], [0]]);
+
+        // the original is still paused waiting for input
+        assert_eq!(vm.next(), Some(Ok(None)));
+        assert!(!vm.is_halted());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut vm = IntcodeVM::new(assemble(BRANCHY).unwrap(), None);
+        vm.next();
+        let paused = vm.snapshot();
+
+        vm.push_input(3);
+        assert_eq!(vm.run().unwrap(), [1]);
+        assert!(vm.is_halted());
+
+        vm.restore(&paused);
+        vm.push_input(2);
+        assert_eq!(vm.run().unwrap(), [0]);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut vm = IntcodeVM::new(assemble(BRANCHY).unwrap(), None);
+        vm.next();
+        vm.push_input(3);
+        vm.push_input(-8);
+        let snapshot = vm.snapshot();
+
+        let text = snapshot.to_string();
+        assert!(text.starts_with("ip 2\nrb 100\ninputs 3,-8\nmemory 109,100,203,0,"));
+        assert_eq!(text.parse::<Snapshot>(), Ok(snapshot.clone()));
+
+        let path = std::env::temp_dir().join(format!("intcode-snapshot-{}", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let mut resumed = IntcodeVM::from_snapshot(&Snapshot::load(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.run().unwrap(), [1]);
+
+        assert_eq!(
+            "ip 0\nrb 0".parse::<Snapshot>(),
+            Err("snapshot is missing memory".to_string())
+        );
+        assert!("ip x\nrb 0\nmemory 1".parse::<Snapshot>().is_err());
+    }
+}
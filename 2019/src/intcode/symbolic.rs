@@ -0,0 +1,547 @@
+//! Symbolic execution: runs a program with some memory cells holding named unknowns
+//! and reports what the program computed in terms of them.
+//!
+//! Control flow and addresses still have to be concrete. Reading memory through an
+//! unknown address gives an opaque value, which is fine as long as nothing branches on
+//! it or writes through it.
+use super::{parse_op_code, IntcodeError, OpCode, MAX_MEMORY};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expr {
+    Const(i64),
+    Symbol(Rc<str>),
+    /// Whatever was in memory at an address that isn't known.
+    Load(Rc<Expr>),
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    LessThan(Rc<Expr>, Rc<Expr>),
+    Equals(Rc<Expr>, Rc<Expr>),
+}
+
+impl Expr {
+    pub fn symbol(name: &str) -> Self {
+        Expr::Symbol(name.into())
+    }
+
+    pub fn as_const(&self) -> Option<i64> {
+        match self {
+            Expr::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The expression as `constant + Σ coefficient × symbol`, if it is linear.
+    pub fn linear(&self) -> Option<Linear> {
+        match self {
+            Expr::Const(value) => Some(Linear {
+                constant: *value,
+                terms: BTreeMap::new(),
+            }),
+            Expr::Symbol(name) => Some(Linear {
+                constant: 0,
+                terms: vec![(name.to_string(), 1)].into_iter().collect(),
+            }),
+            Expr::Add(a, b) => a.linear()?.add(&b.linear()?),
+            Expr::Mul(a, b) => {
+                let (a, b) = (a.linear()?, b.linear()?);
+                match (a.terms.is_empty(), b.terms.is_empty()) {
+                    (true, _) => b.scale(a.constant),
+                    (_, true) => a.scale(b.constant),
+                    _ => None,
+                }
+            }
+            Expr::Load(_) | Expr::LessThan(..) | Expr::Equals(..) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Load(address) => write!(f, "mem[{}]", address),
+            Expr::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Mul(a, b) => write!(f, "({} * {})", a, b),
+            Expr::LessThan(a, b) => write!(f, "({} < {})", a, b),
+            Expr::Equals(a, b) => write!(f, "({} == {})", a, b),
+        }
+    }
+}
+
+/// `constant + Σ coefficient × symbol`, with no zero coefficients.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Linear {
+    pub constant: i64,
+    pub terms: BTreeMap<String, i64>,
+}
+
+impl Linear {
+    fn add(mut self, other: &Linear) -> Option<Linear> {
+        self.constant = self.constant.checked_add(other.constant)?;
+        for (name, coefficient) in &other.terms {
+            let sum = self
+                .terms
+                .get(name)
+                .unwrap_or(&0)
+                .checked_add(*coefficient)?;
+            match sum {
+                0 => self.terms.remove(name),
+                _ => self.terms.insert(name.clone(), sum),
+            };
+        }
+        Some(self)
+    }
+
+    fn scale(mut self, factor: i64) -> Option<Linear> {
+        if factor == 0 {
+            return Some(Linear {
+                constant: 0,
+                terms: BTreeMap::new(),
+            });
+        }
+        self.constant = self.constant.checked_mul(factor)?;
+        for coefficient in self.terms.values_mut() {
+            *coefficient = coefficient.checked_mul(factor)?;
+        }
+        Some(self)
+    }
+
+    /// Values for every symbol, each drawn from `domain`, that make the expression equal
+    /// `target`. The first solution in order of symbol name wins. All but the last
+    /// symbol are searched, the last is solved for. Symbols with a zero coefficient
+    /// don't affect the value and take the start of `domain`.
+    pub fn solve(&self, target: i64, domain: Range<i64>) -> Option<BTreeMap<String, i64>> {
+        let (names, unused): (Vec<_>, Vec<_>) = self
+            .terms
+            .iter()
+            .partition(|(_, coefficient)| **coefficient != 0);
+        if !unused.is_empty() && domain.is_empty() {
+            return None;
+        }
+        let mut solution = unused
+            .into_iter()
+            .map(|(name, _)| (name.clone(), domain.start))
+            .collect::<BTreeMap<_, _>>();
+        let names = names
+            .into_iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let mut values = vec![domain.start; names.len()];
+        let (last, searched) = match names.len() {
+            0 => return Some(solution).filter(|_| self.constant == target),
+            n => (n - 1, n - 1),
+        };
+        if domain.is_empty() {
+            return None;
+        }
+        loop {
+            let partial = (0..searched).try_fold(self.constant, |sum, i| {
+                sum.checked_add(self.terms[&names[i]].checked_mul(values[i])?)
+            });
+            let coefficient = self.terms[&names[last]];
+            let value = partial
+                .and_then(|sum| target.checked_sub(sum))
+                .filter(|remainder| remainder.checked_rem(coefficient) == Some(0))
+                .and_then(|remainder| remainder.checked_div(coefficient))
+                .filter(|value| domain.contains(value));
+            if let Some(value) = value {
+                values[last] = value;
+                solution.extend(names.into_iter().zip(values));
+                return Some(solution);
+            }
+
+            // next combination of the searched symbols, like an odometer
+            let mut i = 0;
+            loop {
+                if i == searched {
+                    return None;
+                }
+                values[i] += 1;
+                if values[i] < domain.end {
+                    break;
+                }
+                values[i] = domain.start;
+                i += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Linear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms = self
+            .terms
+            .iter()
+            .map(|(name, coefficient)| match coefficient {
+                1 => name.clone(),
+                _ => format!("{}*{}", coefficient, name),
+            })
+            .collect::<Vec<_>>();
+        if self.constant != 0 || terms.is_empty() {
+            terms.push(self.constant.to_string());
+        }
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SymbolicError {
+    /// The program failed the same way it would have on the concrete VM.
+    Concrete(IntcodeError),
+    SymbolicOpCode {
+        address: usize,
+    },
+    SymbolicBranch {
+        condition: Expr,
+        address: usize,
+    },
+    SymbolicAddress {
+        target: Expr,
+        address: usize,
+    },
+    StepLimit {
+        steps: usize,
+    },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Concrete(e) => write!(f, "{}", e),
+            SymbolicError::SymbolicOpCode { address } => {
+                write!(f, "instruction at address {} is not concrete", address)
+            }
+            SymbolicError::SymbolicBranch { condition, address } => write!(
+                f,
+                "jump at address {} depends on symbolic value {}",
+                address, condition
+            ),
+            SymbolicError::SymbolicAddress { target, address } => {
+                write!(f, "address used at {} is symbolic: {}", address, target)
+            }
+            SymbolicError::StepLimit { steps } => {
+                write!(f, "gave up after {} instructions", steps)
+            }
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+impl From<IntcodeError> for SymbolicError {
+    fn from(e: IntcodeError) -> Self {
+        SymbolicError::Concrete(e)
+    }
+}
+
+pub struct SymbolicVM {
+    memory: Vec<Expr>,
+    instruction_pointer: usize,
+    relative_base: i64,
+    inputs_read: usize,
+    pub outputs: Vec<Expr>,
+    pub step_limit: usize,
+}
+
+impl SymbolicVM {
+    pub fn new(memory: &[i64]) -> Self {
+        Self {
+            memory: memory.iter().map(|value| Expr::Const(*value)).collect(),
+            instruction_pointer: 0,
+            relative_base: 0,
+            inputs_read: 0,
+            outputs: Vec::new(),
+            step_limit: 1_000_000,
+        }
+    }
+
+    /// Replaces the cell at `address` with the unknown `name`.
+    pub fn set_symbol(&mut self, address: usize, name: &str) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Expr::Const(0));
+        }
+        self.memory[address] = Expr::symbol(name);
+    }
+
+    pub fn peek(&self, address: usize) -> Expr {
+        self.memory.get(address).cloned().unwrap_or(Expr::Const(0))
+    }
+
+    /// Runs to the end of the program. Each input instruction reads a fresh unknown
+    /// named `input0`, `input1`, ...
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        for _ in 0..self.step_limit {
+            if !self.step()? {
+                return Ok(());
+            }
+        }
+        Err(SymbolicError::StepLimit {
+            steps: self.step_limit,
+        })
+    }
+
+    /// Executes one instruction, returning `false` once the program has halted.
+    fn step(&mut self) -> Result<bool, SymbolicError> {
+        let ip = self.instruction_pointer;
+        let word = self
+            .peek(ip)
+            .as_const()
+            .ok_or(SymbolicError::SymbolicOpCode { address: ip })?;
+        let (op_code, argument_count, param_modes) = parse_op_code(word, ip)?;
+        let parameters = (0..argument_count)
+            .map(|i| self.peek(ip + 1 + i))
+            .collect::<Vec<_>>();
+        let read = |i: usize| self.read(&parameters[i], param_modes[i]);
+
+        match op_code {
+            OpCode::Halt => return Ok(false),
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => {
+                let (a, b) = (read(0)?, read(1)?);
+                let value = match (op_code, a.as_const(), b.as_const()) {
+                    (OpCode::Add, Some(a), Some(b)) => {
+                        Expr::Const(a.checked_add(b).ok_or(self.overflow())?)
+                    }
+                    (OpCode::Multiply, Some(a), Some(b)) => {
+                        Expr::Const(a.checked_mul(b).ok_or(self.overflow())?)
+                    }
+                    (OpCode::LessThan, Some(a), Some(b)) => Expr::Const((a < b) as i64),
+                    (OpCode::Equals, Some(a), Some(b)) => Expr::Const((a == b) as i64),
+                    (OpCode::Add, ..) => Expr::Add(Rc::new(a), Rc::new(b)),
+                    (OpCode::Multiply, ..) => Expr::Mul(Rc::new(a), Rc::new(b)),
+                    (OpCode::LessThan, ..) => Expr::LessThan(Rc::new(a), Rc::new(b)),
+                    _ => Expr::Equals(Rc::new(a), Rc::new(b)),
+                };
+                self.write(&parameters[2], param_modes[2], value)?;
+            }
+            OpCode::Input => {
+                let value = Expr::symbol(&format!("input{}", self.inputs_read));
+                self.inputs_read += 1;
+                self.write(&parameters[0], param_modes[0], value)?;
+            }
+            OpCode::Output => {
+                let value = read(0)?;
+                self.outputs.push(value);
+            }
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let condition = read(0)?;
+                let jump = match condition.as_const() {
+                    Some(value) => (value != 0) == (op_code == OpCode::JumpIfTrue),
+                    None => {
+                        return Err(SymbolicError::SymbolicBranch {
+                            condition,
+                            address: ip,
+                        })
+                    }
+                };
+                if jump {
+                    self.instruction_pointer = self.concrete_address(read(1)?)?;
+                    return Ok(true);
+                }
+            }
+            OpCode::AdjustRelativeBase => {
+                let offset = read(0)?;
+                let offset = offset.as_const().ok_or(SymbolicError::SymbolicAddress {
+                    target: offset,
+                    address: ip,
+                })?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or(self.overflow())?;
+            }
+        }
+        self.instruction_pointer += 1 + argument_count;
+        Ok(true)
+    }
+
+    /// The address a position- or relative-mode parameter refers to, as an expression.
+    fn address(&self, parameter: &Expr, mode: u8) -> Result<Expr, SymbolicError> {
+        match (mode, parameter.as_const()) {
+            (0, _) => Ok(parameter.clone()),
+            (2, Some(offset)) => Ok(Expr::Const(
+                self.relative_base
+                    .checked_add(offset)
+                    .ok_or(self.overflow())?,
+            )),
+            (2, None) => Ok(Expr::Add(
+                Rc::new(Expr::Const(self.relative_base)),
+                Rc::new(parameter.clone()),
+            )),
+            _ => Err(IntcodeError::ImmediateModeWrite {
+                address: self.instruction_pointer,
+            }
+            .into()),
+        }
+    }
+
+    fn concrete_address(&self, target: Expr) -> Result<usize, SymbolicError> {
+        match target.as_const() {
+            Some(target) if target < 0 => Err(IntcodeError::NegativeAddress {
+                target,
+                address: self.instruction_pointer,
+            }
+            .into()),
+            Some(target) => Ok(target as usize),
+            None => Err(SymbolicError::SymbolicAddress {
+                target,
+                address: self.instruction_pointer,
+            }),
+        }
+    }
+
+    fn read(&self, parameter: &Expr, mode: u8) -> Result<Expr, SymbolicError> {
+        if mode == 1 {
+            return Ok(parameter.clone());
+        }
+        let address = self.address(parameter, mode)?;
+        match address.as_const() {
+            Some(_) => Ok(self.peek(self.concrete_address(address)?)),
+            None => Ok(Expr::Load(Rc::new(address))),
+        }
+    }
+
+    fn write(&mut self, parameter: &Expr, mode: u8, value: Expr) -> Result<(), SymbolicError> {
+        let address = self.concrete_address(self.address(parameter, mode)?)?;
+        if address >= self.memory.len() {
+            if address >= MAX_MEMORY {
+                return Err(IntcodeError::AddressOutOfRange {
+                    target: address as i64,
+                    address: self.instruction_pointer,
+                }
+                .into());
+            }
+            self.memory.resize(address + 1, Expr::Const(0));
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::ArithmeticOverflow {
+            address: self.instruction_pointer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{parse_intcode_program, perform_computation};
+
+    static DAY_2: &str = include_str!("../../aoc2019-02/input.txt");
+
+    #[test]
+    fn test_day_2_is_linear() {
+        let memory = parse_intcode_program(DAY_2).unwrap();
+        let mut vm = SymbolicVM::new(&memory);
+        vm.set_symbol(1, "noun");
+        vm.set_symbol(2, "verb");
+        vm.run().unwrap();
+
+        let result = vm.peek(0).linear().unwrap();
+        assert_eq!(result.terms.len(), 2);
+        assert_eq!(result.terms["verb"], 1);
+        for (noun, verb) in &[(12, 2), (0, 0), (99, 99), (77, 49)] {
+            let expected = perform_computation(*noun, *verb, &memory).unwrap();
+            let evaluated = result.constant + result.terms["noun"] * noun + verb;
+            assert_eq!(evaluated, expected);
+        }
+
+        let target = perform_computation(77, 49, &memory).unwrap();
+        let solution = result.solve(target, 0..100).unwrap();
+        assert_eq!(solution["noun"], 77);
+        assert_eq!(solution["verb"], 49);
+        assert_eq!(result.solve(-1, 0..100), None);
+    }
+
+    #[test]
+    fn test_solve_edge_cases() {
+        let linear = |constant, terms: &[(&str, i64)]| Linear {
+            constant,
+            terms: terms
+                .iter()
+                .map(|(name, coefficient)| (name.to_string(), *coefficient))
+                .collect(),
+        };
+        let solution = |pairs: &[(&str, i64)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        // a zero coefficient leaves its symbol free
+        let zero = linear(5, &[("a", 0), ("b", 2)]);
+        assert_eq!(zero.solve(9, 0..10), Some(solution(&[("a", 0), ("b", 2)])));
+        let only_zero = linear(5, &[("a", 0)]);
+        assert_eq!(only_zero.solve(5, 3..10), Some(solution(&[("a", 3)])));
+        assert_eq!(only_zero.solve(6, 3..10), None);
+        assert_eq!(only_zero.solve(5, 3..3), None);
+
+        // i64::MIN / -1 overflows
+        let negated = linear(0, &[("a", -1)]);
+        assert_eq!(negated.solve(i64::MIN, i64::MIN..i64::MAX), None);
+        assert_eq!(negated.solve(-4, 0..10), Some(solution(&[("a", 4)])));
+    }
+
+    #[test]
+    fn test_expressions() {
+        // out = (x + 3) * 4; [flag] = x < out
+        let memory = parse_intcode_program("1001,20,3,21,1002,21,4,21,4,21,7,20,21,22,99").unwrap();
+        let mut vm = SymbolicVM::new(&memory);
+        vm.set_symbol(20, "x");
+        vm.run().unwrap();
+        assert_eq!(vm.outputs, [vm.peek(21)]);
+        assert_eq!(vm.outputs[0].to_string(), "((x + 3) * 4)");
+        assert_eq!(vm.outputs[0].linear().unwrap().to_string(), "4*x + 12");
+        assert_eq!(vm.peek(22).to_string(), "(x < ((x + 3) * 4))");
+        assert_eq!(vm.peek(22).linear(), None);
+    }
+
+    #[test]
+    fn test_symbolic_control_flow() {
+        let memory = parse_intcode_program("3,9,1005,9,7,104,0,104,1,0").unwrap();
+        let mut vm = SymbolicVM::new(&memory);
+        assert_eq!(
+            vm.run(),
+            Err(SymbolicError::SymbolicBranch {
+                condition: Expr::symbol("input0"),
+                address: 2
+            })
+        );
+
+        // a symbolic position can be read from, but not written to
+        let memory = parse_intcode_program("4,0,1101,5,5,0,99").unwrap();
+        let mut vm = SymbolicVM::new(&memory);
+        vm.set_symbol(1, "p");
+        vm.set_symbol(5, "p");
+        assert_eq!(
+            vm.run(),
+            Err(SymbolicError::SymbolicAddress {
+                target: Expr::symbol("p"),
+                address: 2
+            })
+        );
+        assert_eq!(vm.outputs[0].to_string(), "mem[p]");
+    }
+
+    #[test]
+    fn test_concrete_errors() {
+        let mut vm = SymbolicVM::new(&[1105, 1, 0]);
+        assert_eq!(vm.run(), Err(SymbolicError::StepLimit { steps: 1_000_000 }));
+        let mut vm = SymbolicVM::new(&[42]);
+        assert_eq!(
+            vm.run(),
+            Err(SymbolicError::Concrete(IntcodeError::UnknownOpCode {
+                value: 42,
+                address: 0
+            }))
+        );
+    }
+}
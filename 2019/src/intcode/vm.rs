@@ -0,0 +1,280 @@
+//! `IntcodeVM` as an `aoc_vm::Vm`, for the tooling shared with the other years' machines:
+//! the `intcode-debugger` and `intcode-profile` binaries are `aoc_vm`'s debugger and
+//! tracer running over this impl.
+use super::snapshot::Snapshot;
+use super::{disasm, IntcodeError, IntcodeVM, StepResult};
+use aoc_vm::{MemoryWrite, Step, Undone, Vm};
+
+impl Vm for IntcodeVM {
+    type Error = IntcodeError;
+    type Snapshot = Snapshot;
+
+    fn step(&mut self) -> Result<Step, IntcodeError> {
+        Ok(match IntcodeVM::step(self)? {
+            StepResult::Executed => Step::Executed,
+            StepResult::Output(value) => Step::Output(value),
+            StepResult::AwaitingInput => Step::AwaitingInput,
+            StepResult::Halted => Step::Halted,
+        })
+    }
+
+    fn ip(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    fn is_halted(&self) -> bool {
+        IntcodeVM::is_halted(self)
+    }
+
+    fn registers(&self) -> Vec<(&'static str, i64)> {
+        vec![("rb", self.relative_base)]
+    }
+
+    fn memory_len(&self) -> usize {
+        self.program_memory.len()
+    }
+
+    fn instruction(&self, address: usize) -> Option<(String, usize)> {
+        if address >= self.program_memory.len() {
+            return None;
+        }
+        let line = disasm::decode_line(&self.program_memory, address);
+        Some((line.decoded.to_string(), line.words.len()))
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        IntcodeVM::snapshot(self)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        IntcodeVM::restore(self, snapshot)
+    }
+
+    /// Cells past the end of memory read as zero, as they do for the program.
+    fn peek(&self, address: usize) -> Option<i64> {
+        Some(IntcodeVM::peek(self, address))
+    }
+
+    fn poke(&mut self, address: usize, value: i64) -> Result<(), String> {
+        IntcodeVM::poke(self, address, value).map_err(|e| e.to_string())
+    }
+
+    fn push_input(&mut self, value: i64) -> Result<(), String> {
+        IntcodeVM::push_input(self, value);
+        Ok(())
+    }
+
+    fn pending_inputs(&self) -> Option<Vec<i64>> {
+        Some(IntcodeVM::pending_inputs(self).iter().copied().collect())
+    }
+
+    fn start_recording(&mut self, capacity: usize) -> Result<(), String> {
+        IntcodeVM::start_recording(self, capacity);
+        Ok(())
+    }
+
+    fn recorded_steps(&self) -> Option<usize> {
+        Some(IntcodeVM::recorded_steps(self)).filter(|_| self.is_recording())
+    }
+
+    fn reverse_step(&mut self) -> Option<Undone> {
+        IntcodeVM::reverse_step(self)
+    }
+
+    fn set_tracing(&mut self, enabled: bool) {
+        self.tracing = enabled;
+        self.traced_operands.clear();
+        self.last_write = None;
+    }
+
+    fn last_reads(&self) -> Vec<i64> {
+        self.traced_operands.clone()
+    }
+
+    fn last_write(&self) -> Option<MemoryWrite> {
+        self.last_write.filter(|_| self.tracing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use aoc_vm::cycle::{detect_loop, Outcome};
+    use aoc_vm::debugger::{Debugger, StopReason, DEFAULT_HISTORY};
+    use aoc_vm::trace::{trace, Profiler};
+
+    static COUNTDOWN: &str = "
+                IN   [n]
+        loop:   JF   [n], #done
+                OUT  [n]
+                ADD  [n], #-1, [n]
+                JT   #1, #loop
+        done:   HLT
+            n:  .data 0
+    ";
+
+    #[test]
+    fn test_shared_tooling() {
+        let memory = assemble(
+            "loop:   OUT  [n]
+                    ADD  [n], #-1, [n]
+                    JT   [n], #loop
+                    HLT
+               n:   .data 2",
+        )
+        .unwrap();
+
+        let mut lines = Vec::new();
+        let mut vm = IntcodeVM::new(memory.clone(), None);
+        let stop = trace(&mut vm, 100, |line| lines.push(line.to_string()));
+        assert_eq!(stop, Ok(Step::Halted));
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "     0  OUT [10]                 rb=0  reads 2  -> 2");
+
+        let mut debugger = Debugger::new(IntcodeVM::new(memory.clone(), None));
+        assert_eq!(debugger.execute("b 6"), Ok("breakpoint set at 6".into()));
+        assert_eq!(
+            debugger.execute("c"),
+            Ok("output: 2\nbreakpoint at 6\n     6  JT [10], #0".into())
+        );
+        assert_eq!(
+            debugger.execute("list 2"),
+            Ok("     6  JT [10], #0\n     9  HLT".into())
+        );
+
+        // without the decrement the exact machine state repeats
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.program_memory[4] = 0;
+        let outcome = detect_loop(&mut vm, IntcodeVM::snapshot, 1000);
+        assert!(matches!(
+            outcome,
+            Ok(Outcome::Repeated {
+                first_seen: 0,
+                steps: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_trace_reads_and_writes() {
+        let memory = assemble(COUNTDOWN).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.push_input(1);
+        let mut lines = Vec::new();
+        assert_eq!(
+            trace(&mut vm, 100, |line| lines.push(line.clone())),
+            Ok(Step::Halted)
+        );
+
+        let mnemonics = lines
+            .iter()
+            .map(|line| line.instruction.split(' ').next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(mnemonics, ["IN", "JF", "OUT", "ADD", "JT", "JF"]);
+        assert_eq!(lines[3].reads, [1, -1]);
+        assert_eq!(
+            lines[3].write,
+            Some(MemoryWrite {
+                address: 15,
+                old: 1,
+                new: 0
+            })
+        );
+        assert_eq!(
+            lines[3].to_string(),
+            "     7  ADD [15], #-1, [15]      rb=0  reads 1, -1  [15] 1 => 0"
+        );
+        assert_eq!(lines[2].write, None);
+    }
+
+    #[test]
+    fn test_profiler() {
+        let memory = assemble(COUNTDOWN).unwrap();
+        let mut vm = IntcodeVM::new(memory, None);
+        vm.push_input(3);
+        let mut profiler = Profiler::new();
+        trace(&mut vm, usize::MAX, |line| profiler.record(line)).unwrap();
+
+        // IN, then 3 full loops of JF/OUT/ADD/JT, then the final JF
+        assert_eq!(profiler.cycles, 1 + 3 * 4 + 1);
+        assert_eq!(profiler.opcode_counts["JF"], 4);
+        assert_eq!(profiler.opcode_counts["ADD"], 3);
+        assert_eq!(profiler.hot_addresses(2), [(2, 4), (5, 3)]);
+    }
+
+    #[test]
+    fn test_debugger_watchpoints_and_reverse() {
+        let memory = assemble(COUNTDOWN).unwrap();
+        let mut debugger = Debugger::new(IntcodeVM::new(memory, None));
+        debugger.vm.start_recording(DEFAULT_HISTORY);
+
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::AwaitingInput));
+        debugger.vm.push_input(2);
+        debugger.add_watchpoint(15);
+        assert_eq!(
+            debugger.continue_execution(),
+            Ok(StopReason::Watchpoint {
+                address: 15,
+                old: 0,
+                new: 2
+            })
+        );
+        assert!(debugger.remove_watchpoint(15));
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Halted));
+        assert_eq!(debugger.take_outputs(), [2, 1]);
+
+        // back to where the counter hit zero
+        assert_eq!(
+            debugger.reverse_to_write(15),
+            StopReason::LastWrite(MemoryWrite {
+                address: 15,
+                old: 1,
+                new: 0
+            })
+        );
+        assert_eq!(debugger.vm.instruction_pointer(), 7);
+        assert_eq!(debugger.vm.peek(15), 1);
+
+        debugger.add_breakpoint(2);
+        assert_eq!(debugger.reverse_continue(), StopReason::Breakpoint(2));
+        assert!(debugger.remove_breakpoint(2));
+        assert_eq!(debugger.reverse_continue(), StopReason::StartOfHistory);
+        assert_eq!(debugger.vm.instruction_pointer(), 0);
+        assert_eq!(debugger.vm.pending_inputs(), &[2]);
+
+        // and forwards again, replaying the same run
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Halted));
+        assert_eq!(debugger.take_outputs(), [2, 1]);
+    }
+
+    #[test]
+    fn test_debugger_commands() {
+        let memory = assemble(COUNTDOWN).unwrap();
+        let mut debugger = Debugger::new(IntcodeVM::new(memory, None));
+        let mut run = |line: &str| debugger.execute(line).unwrap();
+
+        assert_eq!(run("record 100"), "recording the last 100 steps");
+        assert_eq!(run("input 1"), "1 input(s) queued");
+        assert_eq!(run("step"), "     2  JF [15], #14");
+        assert_eq!(run("regs"), "ip=2 rb=0 inputs=[] history=1");
+        assert_eq!(run("mem 14..16"), "    14: 99 1");
+        assert_eq!(run("poke 15 2"), "[15] = 2");
+        assert_eq!(
+            run("c"),
+            "output: 2\noutput: 1\nprogram halted\n    14  HLT"
+        );
+        assert_eq!(
+            run("rc 15"),
+            "[15] written here: 1 -> 0\n     7  ADD [15], #-1, [15]"
+        );
+        assert_eq!(run("rs 2"), "     2  JF [15], #14");
+        assert_eq!(
+            run("rs 9"),
+            "reached the start of the recorded history\n     0  IN [15]"
+        );
+        assert_eq!(run("mem 15"), "    15: 0");
+        assert_eq!(run("regs"), "ip=0 rb=0 inputs=[1] history=0");
+    }
+}
@@ -1,6 +1,4 @@
-#[macro_use]
-extern crate maplit;
-#[macro_use]
-extern crate lazy_static;
-
+pub mod amplifier;
 pub mod intcode;
+pub mod network;
+pub mod robot;
@@ -0,0 +1,232 @@
+//! A network of Intcode computers exchanging `(destination, x, y)` packets, with an
+//! optional NAT that wakes the network up whenever it goes idle.
+use crate::intcode::{IntcodeError, IntcodeVM};
+use std::collections::VecDeque;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Packet {
+    pub destination: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Something worth reporting that happened during a round.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NetworkEvent {
+    /// A packet addressed to the NAT, which keeps only the latest one.
+    NatReceived(Packet),
+    /// The network was idle, so the NAT resent its latest packet to address 0.
+    NatDelivered(Packet),
+    /// A packet addressed to neither a node nor the NAT.
+    Dropped(Packet),
+}
+
+struct Node {
+    vm: IntcodeVM,
+    queue: VecDeque<(i64, i64)>,
+    /// Output words of a packet that hasn't been fully sent yet.
+    sending: Vec<i64>,
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    nat_address: Option<i64>,
+    nat_packet: Option<Packet>,
+}
+
+impl Network {
+    /// Boots `size` copies of `program`, giving each its address as the first input.
+    pub fn new(program: &[i64], size: usize) -> Self {
+        let nodes = (0..size)
+            .map(|address| {
+                let mut vm = IntcodeVM::new(program.to_vec(), None);
+                vm.push_input(address as i64);
+                Node {
+                    vm,
+                    queue: VecDeque::new(),
+                    sending: Vec::new(),
+                }
+            })
+            .collect();
+        Self {
+            nodes,
+            nat_address: None,
+            nat_packet: None,
+        }
+    }
+
+    /// Packets sent to `address` go to a NAT instead of being dropped.
+    pub fn with_nat(mut self, address: i64) -> Self {
+        self.nat_address = Some(address);
+        self
+    }
+
+    /// The last packet the NAT received.
+    pub fn nat_packet(&self) -> Option<Packet> {
+        self.nat_packet
+    }
+
+    /// Gives every running node, in address order, its next queued packet (or `-1` when
+    /// it has none) and runs it until it wants more input. Packets are routed as soon as
+    /// they are sent, so later nodes may receive them in the same round.
+    ///
+    /// A round in which every node started with an empty queue and nobody sent anything
+    /// leaves the network idle, and the NAT (if any) resends its packet to address 0.
+    pub fn round(&mut self) -> Result<Vec<NetworkEvent>, IntcodeError> {
+        let mut events = Vec::new();
+        let mut idle = true;
+        for address in 0..self.nodes.len() {
+            let node = &mut self.nodes[address];
+            if node.vm.is_halted() {
+                continue;
+            }
+            match node.queue.pop_front() {
+                Some((x, y)) => {
+                    idle = false;
+                    node.vm.push_input(x);
+                    node.vm.push_input(y);
+                }
+                None => node.vm.push_input(-1),
+            }
+            let mut outputs = Vec::new();
+            node.vm.run_with(&mut VecDeque::new(), &mut outputs)?;
+            for value in outputs {
+                let node = &mut self.nodes[address];
+                node.sending.push(value);
+                if let [destination, x, y] = node.sending[..] {
+                    node.sending.clear();
+                    idle = false;
+                    events.extend(self.send(Packet { destination, x, y }));
+                }
+            }
+        }
+
+        if idle {
+            if let Some(packet) = self.nat_packet.filter(|_| !self.nodes.is_empty()) {
+                self.nodes[0].queue.push_back((packet.x, packet.y));
+                events.push(NetworkEvent::NatDelivered(packet));
+            }
+        }
+        Ok(events)
+    }
+
+    fn send(&mut self, packet: Packet) -> Option<NetworkEvent> {
+        if Some(packet.destination) == self.nat_address {
+            self.nat_packet = Some(packet);
+            return Some(NetworkEvent::NatReceived(packet));
+        }
+        match self.nodes.get_mut(packet.destination as usize) {
+            Some(node) if packet.destination >= 0 => {
+                node.queue.push_back((packet.x, packet.y));
+                None
+            }
+            _ => Some(NetworkEvent::Dropped(packet)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Node 0 starts a packet round the ring. Every node adds its address to `y` and
+    /// passes the packet on to the next node; the last one sends it to the NAT.
+    static RING: &str = "
+                IN   [addr]
+                JT   [addr], #recv
+                OUT  #1
+                OUT  #100
+                OUT  #0
+        recv:   IN   [x]
+                EQ   [x], #-1, [flag]
+                JT   [flag], #recv
+                IN   [y]
+                ADD  [y], [addr], [y]
+                ADD  [addr], #1, [dest]
+                EQ   [addr], #2, [flag]
+                JF   [flag], #send
+                ADD  #255, #0, [dest]
+        send:   OUT  [dest]
+                OUT  [x]
+                OUT  [y]
+                JT   #1, #recv
+        addr:   .data 0
+        x:      .data 0
+        y:      .data 0
+        dest:   .data 0
+        flag:   .data 0
+    ";
+
+    /// Node 0 sends one packet to the NAT at boot; after that every node ignores what
+    /// it is sent.
+    static SILENT: &str = "
+                IN   [addr]
+                JT   [addr], #recv
+                OUT  #255
+                OUT  #5
+                OUT  #7
+        recv:   IN   [x]
+                JT   #1, #recv
+        addr:   .data 0
+        x:      .data 0
+    ";
+
+    fn nat_packet(x: i64, y: i64) -> Packet {
+        Packet {
+            destination: 255,
+            x,
+            y,
+        }
+    }
+
+    #[test]
+    fn test_ring() {
+        let mut network = Network::new(&assemble(RING).unwrap(), 3).with_nat(255);
+        // each packet makes it all the way round in one round; the next round is idle
+        let mut events = Vec::new();
+        for _ in 0..6 {
+            events.extend(network.round().unwrap());
+        }
+        assert_eq!(
+            events,
+            [
+                NetworkEvent::NatReceived(nat_packet(100, 3)),
+                NetworkEvent::NatDelivered(nat_packet(100, 3)),
+                NetworkEvent::NatReceived(nat_packet(100, 6)),
+                NetworkEvent::NatDelivered(nat_packet(100, 6)),
+                NetworkEvent::NatReceived(nat_packet(100, 9)),
+                NetworkEvent::NatDelivered(nat_packet(100, 9)),
+            ]
+        );
+        assert_eq!(network.nat_packet(), Some(nat_packet(100, 9)));
+    }
+
+    #[test]
+    fn test_idle_delivery_repeats() {
+        let mut network = Network::new(&assemble(SILENT).unwrap(), 4).with_nat(255);
+        assert_eq!(
+            network.round().unwrap(),
+            [NetworkEvent::NatReceived(nat_packet(5, 7))]
+        );
+        // node 0 swallows each delivery, so the network goes idle every other round
+        let deliveries = (0..6)
+            .flat_map(|_| network.round().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deliveries,
+            [NetworkEvent::NatDelivered(nat_packet(5, 7)); 3]
+        );
+    }
+
+    #[test]
+    fn test_without_nat() {
+        let mut network = Network::new(&assemble(SILENT).unwrap(), 2);
+        assert_eq!(
+            network.round().unwrap(),
+            [NetworkEvent::Dropped(nat_packet(5, 7))]
+        );
+        assert!(network.round().unwrap().is_empty());
+        assert_eq!(network.nat_packet(), None);
+    }
+}
@@ -0,0 +1,440 @@
+//! Harness for Intcode programs that drive a robot around a 2D grid.
+//!
+//! A `Robot` runs the VM and hands its I/O to a `Protocol`, which decides what to feed
+//! the program and how each message it prints changes the `World`. Coordinates are
+//! screen-style: `x` grows to the right and `y` grows downwards.
+use crate::intcode::{IntcodeError, IntcodeVM, RunState, StepResult};
+use std::collections::HashMap;
+
+pub type Point = (i64, i64);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn step(self, (x, y): Point) -> Point {
+        match self {
+            Direction::Up => (x, y - 1),
+            Direction::Right => (x + 1, y),
+            Direction::Down => (x, y + 1),
+            Direction::Left => (x - 1, y),
+        }
+    }
+
+    fn arrow(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+}
+
+/// Sparse grid of tiles; anything never set reads as `None`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Grid<T> {
+    pub tiles: HashMap<Point, T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.tiles.get(&point)
+    }
+
+    pub fn set(&mut self, point: Point, tile: T) {
+        self.tiles.insert(point, tile);
+    }
+
+    /// Top-left and bottom-right corners of the area holding tiles.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let xs = self.tiles.keys().map(|(x, _)| *x);
+        let ys = self.tiles.keys().map(|(_, y)| *y);
+        Some((
+            (xs.clone().min()?, ys.clone().min()?),
+            (xs.max()?, ys.max()?),
+        ))
+    }
+
+    /// Draws every tile inside `bounds` using `draw`, one line per row.
+    pub fn render<F>(&self, draw: F) -> String
+    where
+        F: Fn(Point, Option<&T>) -> char,
+    {
+        let mut picture = String::new();
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.bounds() {
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    picture.push(draw((x, y), self.get((x, y))));
+                }
+                picture.push('\n');
+            }
+        }
+        picture
+    }
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The grid together with where the robot is and which way it's facing.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct World<T> {
+    pub grid: Grid<T>,
+    pub position: Point,
+    pub heading: Direction,
+}
+
+impl<T> World<T> {
+    pub fn new() -> Self {
+        Self {
+            grid: Grid::new(),
+            position: (0, 0),
+            heading: Direction::Up,
+        }
+    }
+
+    /// Like `Grid::render`, with the robot drawn as an arrow showing its heading.
+    pub fn render<F>(&self, draw: F) -> String
+    where
+        F: Fn(Option<&T>) -> char,
+    {
+        self.grid
+            .render(|point, tile| match point == self.position {
+                true => self.heading.arrow(),
+                false => draw(tile),
+            })
+    }
+}
+
+impl<T> Default for World<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a program talks to its robot.
+pub trait Protocol {
+    type Tile;
+
+    /// How many output values make up one message.
+    const MESSAGE_LEN: usize;
+
+    /// The next input for the program, or `None` to pause the robot.
+    fn input(&mut self, world: &World<Self::Tile>) -> Option<i64>;
+
+    /// Applies one complete message from the program to the world.
+    fn handle(&mut self, world: &mut World<Self::Tile>, message: &[i64]);
+}
+
+pub struct Robot<P: Protocol> {
+    pub vm: IntcodeVM,
+    pub world: World<P::Tile>,
+    pub protocol: P,
+    message: Vec<i64>,
+}
+
+impl<P: Protocol> Robot<P> {
+    pub fn new(vm: IntcodeVM, protocol: P) -> Self {
+        Self {
+            vm,
+            world: World::new(),
+            protocol,
+            message: Vec::with_capacity(P::MESSAGE_LEN),
+        }
+    }
+
+    /// Runs until the program halts, or the protocol has no input to give it.
+    pub fn run(&mut self) -> Result<RunState, IntcodeError> {
+        loop {
+            match self.vm.step()? {
+                StepResult::Executed => {}
+                StepResult::Output(value) => {
+                    self.message.push(value);
+                    if self.message.len() == P::MESSAGE_LEN {
+                        self.protocol.handle(&mut self.world, &self.message);
+                        self.message.clear();
+                    }
+                }
+                StepResult::AwaitingInput => match self.protocol.input(&self.world) {
+                    Some(value) => self.vm.push_input(value),
+                    None => return Ok(RunState::AwaitingInput),
+                },
+                StepResult::Halted => return Ok(RunState::Halted),
+            }
+        }
+    }
+}
+
+/// Hull painting: the program reads the colour under the robot (0 black, 1 white) and
+/// answers with the colour to paint and which way to turn (0 left, 1 right), after which
+/// the robot moves forward one panel. Unpainted panels are black.
+pub struct Painter;
+
+impl Protocol for Painter {
+    type Tile = i64;
+    const MESSAGE_LEN: usize = 2;
+
+    fn input(&mut self, world: &World<i64>) -> Option<i64> {
+        Some(*world.grid.get(world.position).unwrap_or(&0))
+    }
+
+    fn handle(&mut self, world: &mut World<i64>, message: &[i64]) {
+        world.grid.set(world.position, message[0]);
+        world.heading = match message[1] {
+            0 => world.heading.turn_left(),
+            _ => world.heading.turn_right(),
+        };
+        world.position = world.heading.step(world.position);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DroidTile {
+    Wall,
+    Open,
+    Target,
+}
+
+/// Repair droid: the program reads a move command (1 north, 2 south, 3 west, 4 east)
+/// and answers 0 if it hit a wall, 1 if it moved, or 2 if it moved onto the target.
+/// `strategy` picks the next direction to try.
+pub struct Droid<F> {
+    strategy: F,
+    attempted: Option<Direction>,
+}
+
+impl<F> Droid<F>
+where
+    F: FnMut(&World<DroidTile>) -> Option<Direction>,
+{
+    pub fn new(strategy: F) -> Self {
+        Self {
+            strategy,
+            attempted: None,
+        }
+    }
+}
+
+impl<F> Protocol for Droid<F>
+where
+    F: FnMut(&World<DroidTile>) -> Option<Direction>,
+{
+    type Tile = DroidTile;
+    const MESSAGE_LEN: usize = 1;
+
+    fn input(&mut self, world: &World<DroidTile>) -> Option<i64> {
+        let direction = (self.strategy)(world)?;
+        self.attempted = Some(direction);
+        Some(match direction {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
+        })
+    }
+
+    fn handle(&mut self, world: &mut World<DroidTile>, message: &[i64]) {
+        let direction = match self.attempted.take() {
+            Some(direction) => direction,
+            None => return,
+        };
+        world
+            .grid
+            .tiles
+            .entry(world.position)
+            .or_insert(DroidTile::Open);
+        world.heading = direction;
+        let target = direction.step(world.position);
+        match message[0] {
+            0 => world.grid.set(target, DroidTile::Wall),
+            status => {
+                let tile = match status {
+                    2 => DroidTile::Target,
+                    _ => DroidTile::Open,
+                };
+                world.grid.set(target, tile);
+                world.position = target;
+            }
+        }
+    }
+}
+
+/// Arcade screen: the program prints `x, y, tile` triples, or `-1, 0, score` to update
+/// the score, and reads the joystick position (-1 left, 0 neutral, 1 right) from
+/// `joystick`.
+pub struct Screen<F> {
+    joystick: F,
+    pub score: Option<i64>,
+}
+
+impl<F> Screen<F>
+where
+    F: FnMut(&World<i64>) -> Option<i64>,
+{
+    pub fn new(joystick: F) -> Self {
+        Self {
+            joystick,
+            score: None,
+        }
+    }
+}
+
+impl<F> Protocol for Screen<F>
+where
+    F: FnMut(&World<i64>) -> Option<i64>,
+{
+    type Tile = i64;
+    const MESSAGE_LEN: usize = 3;
+
+    fn input(&mut self, world: &World<i64>) -> Option<i64> {
+        (self.joystick)(world)
+    }
+
+    fn handle(&mut self, world: &mut World<i64>, message: &[i64]) {
+        match message {
+            [-1, 0, score] => self.score = Some(*score),
+            [x, y, tile] => world.grid.set((*x, *y), *tile),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// A program that reads one input before printing each group of `messages`.
+    fn scripted(messages: &[&[i64]]) -> IntcodeVM {
+        let mut source = String::new();
+        for message in messages {
+            source.push_str("IN [scratch]\n");
+            for value in message.iter() {
+                source.push_str(&format!("OUT #{}\n", value));
+            }
+        }
+        source.push_str("HLT\nscratch: .data 0\n");
+        IntcodeVM::new(assemble(&source).unwrap(), None)
+    }
+
+    #[test]
+    fn test_painter() {
+        let vm = scripted(&[
+            &[1, 0],
+            &[0, 0],
+            &[1, 0],
+            &[1, 0],
+            &[0, 1],
+            &[1, 0],
+            &[1, 0],
+        ]);
+        let mut robot = Robot::new(vm, Painter);
+        assert_eq!(robot.run().unwrap(), RunState::Halted);
+        assert_eq!(robot.world.grid.tiles.len(), 6);
+        assert_eq!(robot.world.position, (0, -1));
+        assert_eq!(robot.world.heading, Direction::Left);
+        let picture = robot.world.render(|tile| match tile {
+            Some(1) => '#',
+            _ => '.',
+        });
+        assert_eq!(picture, ".<#\n..#\n##.\n");
+    }
+
+    #[test]
+    fn test_droid() {
+        // The only way out is east; the target is three steps along
+        let source = "
+            loop:   IN   [cmd]
+                    EQ   [cmd], #4, [flag]
+                    JT   [flag], #east
+                    OUT  #0
+                    JT   #1, #loop
+            east:   ADD  [steps], #1, [steps]
+                    EQ   [steps], #3, [flag]
+                    JT   [flag], #found
+                    OUT  #1
+                    JT   #1, #loop
+            found:  OUT  #2
+                    HLT
+            cmd:    .data 0
+            flag:   .data 0
+            steps:  .data 0
+        ";
+        let vm = IntcodeVM::new(assemble(source).unwrap(), None);
+        let mut moves = vec![
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Right,
+            Direction::Right,
+        ]
+        .into_iter();
+        let mut robot = Robot::new(vm, Droid::new(move |_: &World<_>| moves.next()));
+        assert_eq!(robot.run().unwrap(), RunState::Halted);
+        assert_eq!(robot.world.position, (3, 0));
+        assert_eq!(robot.world.grid.get((3, 0)), Some(&DroidTile::Target));
+        let picture = robot.world.render(|tile| match tile {
+            Some(DroidTile::Wall) => '#',
+            Some(DroidTile::Open) => '.',
+            Some(DroidTile::Target) => 'O',
+            None => ' ',
+        });
+        assert_eq!(picture, "#   \n...>\n #  \n");
+    }
+
+    #[test]
+    fn test_screen() {
+        let vm = scripted(&[&[0, 0, 1, 1, 0, 2, -1, 0, 7], &[2, 1, 4, -1, 0, 42]]);
+        let mut robot = Robot::new(vm, Screen::new(|_: &World<_>| Some(0)));
+        assert_eq!(robot.run().unwrap(), RunState::Halted);
+        assert_eq!(robot.protocol.score, Some(42));
+        let picture = robot.world.grid.render(|_, tile| match tile {
+            Some(1) => '|',
+            Some(2) => '#',
+            Some(4) => 'o',
+            _ => ' ',
+        });
+        assert_eq!(picture, "|# \n  o\n");
+    }
+
+    #[test]
+    fn test_paused_robot() {
+        let vm = scripted(&[&[0, 0, 1], &[1, 1, 1]]);
+        let mut robot = Robot::new(vm, Screen::new(|_: &World<_>| None));
+        assert_eq!(robot.run().unwrap(), RunState::AwaitingInput);
+        assert!(robot.world.grid.tiles.is_empty());
+    }
+}
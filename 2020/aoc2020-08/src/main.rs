@@ -1,5 +1,6 @@
+use aoc_vm::cycle::{detect_loop, Outcome};
+use aoc_vm::{Step, Vm};
 use itertools::interleave;
-use std::collections::HashMap;
 use std::io::{self, Read, Write};
 
 #[derive(Clone, Copy, Debug)]
@@ -49,37 +50,25 @@ impl HandheldVM {
         Self::new(parse_handheld_program(input))
     }
     pub fn run(&mut self) -> Result<isize, Box<dyn std::error::Error>> {
-        let mut instruction_visits: HashMap<usize, usize> = HashMap::new();
-
-        loop {
-            let previous_visits = instruction_visits
-                .get(&self.instruction_pointer)
-                .unwrap_or(&0);
-            match previous_visits {
-                0 => {
-                    instruction_visits.insert(self.instruction_pointer, 1);
-                }
-                _ => {
-                    // println!("LOOP DETECTED! Current ACC: {}", self.accumulator);
-                    return Err("LOOP DETECTED".into());
-                }
-            }
-
-            match self.next() {
-                Some(Ok(_)) => {}
-                Some(Err(e)) => return Err(e),
-                None => break,
-            }
+        // Control flow never depends on the accumulator, so revisiting an instruction
+        // means the program loops forever
+        match detect_loop(self, |vm| vm.ip(), usize::MAX)? {
+            Outcome::Repeated { .. } => Err("LOOP DETECTED".into()),
+            _ => Ok(self.accumulator),
         }
-
-        Ok(self.accumulator)
     }
 }
 
-impl Iterator for HandheldVM {
-    type Item = Result<(), Box<dyn std::error::Error>>;
-    fn next(&mut self) -> Option<Self::Item> {
-        let (instruction, value) = &self.executable_memory[self.instruction_pointer];
+impl Vm for HandheldVM {
+    type Error = Box<dyn std::error::Error>;
+    type Snapshot = (usize, isize);
+
+    fn step(&mut self) -> Result<Step, Self::Error> {
+        let (instruction, value) = match self.executable_memory.get(self.instruction_pointer) {
+            Some(operation) => *operation,
+            None if self.is_halted() => return Ok(Step::Halted),
+            None => return Err("OUT OF BOUNDS".into()),
+        };
         match instruction {
             Instruction::ACC => {
                 self.accumulator += value;
@@ -92,12 +81,43 @@ impl Iterator for HandheldVM {
                 self.instruction_pointer = ((self.instruction_pointer as isize) + value) as usize;
             }
         }
-        let halt_pointer = self.executable_memory.len();
-        match self.instruction_pointer {
-            p if p == halt_pointer => None,
-            p if p > 0 && p < halt_pointer => Some(Ok(())),
-            _ => Some(Err("OUT OF BOUNDS".into())),
-        }
+        Ok(Step::Executed)
+    }
+
+    fn ip(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    /// Running off the end of the program is how it terminates.
+    fn is_halted(&self) -> bool {
+        self.instruction_pointer == self.executable_memory.len()
+    }
+
+    fn registers(&self) -> Vec<(&'static str, i64)> {
+        vec![("acc", self.accumulator as i64)]
+    }
+
+    fn memory_len(&self) -> usize {
+        self.executable_memory.len()
+    }
+
+    fn instruction(&self, address: usize) -> Option<(String, usize)> {
+        let (instruction, value) = self.executable_memory.get(address)?;
+        let mnemonic = match instruction {
+            Instruction::ACC => "acc",
+            Instruction::JMP => "jmp",
+            Instruction::NOP => "nop",
+        };
+        Some((format!("{} {:+}", mnemonic, value), 1))
+    }
+
+    fn snapshot(&self) -> (usize, isize) {
+        (self.instruction_pointer, self.accumulator)
+    }
+
+    fn restore(&mut self, snapshot: &(usize, isize)) {
+        self.instruction_pointer = snapshot.0;
+        self.accumulator = snapshot.1;
     }
 }
 
@@ -208,4 +228,26 @@ acc +6";
         let result = vm.run();
         assert_eq!(result.unwrap(), 8);
     }
+
+    #[test]
+    fn test_shared_tooling() {
+        let mut lines = Vec::new();
+        let mut vm = HandheldVM::from(MOCK_VALID);
+        aoc_vm::trace::trace(&mut vm, 100, |line| lines.push(line.to_string())).unwrap();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[2], "     2  jmp +4                   acc=1");
+        assert!(vm.is_halted());
+
+        let mut debugger = aoc_vm::debugger::Debugger::new(HandheldVM::from(MOCK_DATA));
+        debugger.add_breakpoint(1);
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "breakpoint at 1\n     1  acc +1"
+        );
+        let snapshot = debugger.vm.snapshot();
+        debugger.execute("s 4").unwrap();
+        assert_eq!(debugger.execute("regs").unwrap(), "ip=3 acc=2");
+        debugger.vm.restore(&snapshot);
+        assert_eq!(debugger.execute("regs").unwrap(), "ip=1 acc=0");
+    }
 }
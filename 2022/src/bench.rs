@@ -0,0 +1,164 @@
+/*
+ * This file contains template code.
+ * There is no need to edit this file unless you want to change template functionality.
+ */
+use crate::days::Solver;
+use crate::runner::run_part;
+use crate::{PartResult, Status, ANSI_ITALIC, ANSI_RESET};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_WARMUP: usize = 5;
+pub const DEFAULT_ITERATIONS: usize = 100;
+
+/// Timings of repeated runs of one part, as printed by `cargo all -- --bench --json`:
+/// `{"day":1,"part":1,"iterations":100,"min_ns":8102,"median_ns":8513,"mean_ns":8720,"stddev_ns":402}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchStats {
+    pub day: u8,
+    pub part: u8,
+    pub iterations: usize,
+    pub min_ns: u64,
+    pub median_ns: u64,
+    pub mean_ns: u64,
+    pub stddev_ns: u64,
+}
+
+impl BenchStats {
+    /// `None` without any samples.
+    pub fn from_samples(day: u8, part: u8, samples: &[Duration]) -> Option<Self> {
+        let mut nanos: Vec<f64> = samples.iter().map(|d| d.as_nanos() as f64).collect();
+        nanos.sort_by(f64::total_cmp);
+        let count = nanos.len();
+        if count == 0 {
+            return None;
+        }
+
+        let median = match count % 2 {
+            1 => nanos[count / 2],
+            _ => (nanos[count / 2 - 1] + nanos[count / 2]) / 2.0,
+        };
+        let mean = nanos.iter().sum::<f64>() / count as f64;
+        // sample standard deviation; a single run has none
+        let variance = match count {
+            1 => 0.0,
+            _ => nanos.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / (count - 1) as f64,
+        };
+        Some(BenchStats {
+            day,
+            part,
+            iterations: count,
+            min_ns: nanos[0] as u64,
+            median_ns: median.round() as u64,
+            mean_ns: mean.round() as u64,
+            stddev_ns: variance.sqrt().round() as u64,
+        })
+    }
+
+    pub fn median(&self) -> Duration {
+        Duration::from_nanos(self.median_ns)
+    }
+
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("stats always serialize")
+    }
+
+    pub fn from_json_line(line: &str) -> Option<Self> {
+        serde_json::from_str(line.trim()).ok()
+    }
+}
+
+/// `median 8.51µs (min 8.10µs, mean 8.72µs ± 402.00ns, 100 iterations)`
+impl fmt::Display for BenchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "median {:.2?} {}(min {:.2?}, mean {:.2?} ± {:.2?}, {} iterations){}",
+            self.median(),
+            ANSI_ITALIC,
+            Duration::from_nanos(self.min_ns),
+            Duration::from_nanos(self.mean_ns),
+            Duration::from_nanos(self.stddev_ns),
+            self.iterations,
+            ANSI_RESET
+        )
+    }
+}
+
+/// Times `iterations` runs of a part after `warmup` untimed ones. A part whose first run
+/// panics, gives no answer or gives an answer other than `expected` isn't benchmarked;
+/// that run's result is the error.
+pub fn bench_part(
+    day: u8,
+    part: u8,
+    solver: Solver,
+    input: &str,
+    expected: Option<&str>,
+    warmup: usize,
+    iterations: usize,
+) -> Result<BenchStats, PartResult> {
+    let first = run_part(day, part, solver, input).check(expected);
+    if first.status != Status::Solved {
+        return Err(first);
+    }
+    for _ in 0..warmup {
+        black_box(solver(black_box(input)));
+    }
+    let samples: Vec<Duration> = (0..iterations)
+        .map(|_| {
+            let timer = Instant::now();
+            black_box(solver(black_box(input)));
+            timer.elapsed()
+        })
+        .collect();
+    BenchStats::from_samples(day, part, &samples).ok_or(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_samples() {
+        let samples: Vec<Duration> = [40, 10, 30, 20].map(Duration::from_nanos).to_vec();
+        let stats = BenchStats::from_samples(4, 1, &samples).unwrap();
+        assert_eq!(
+            stats,
+            BenchStats {
+                day: 4,
+                part: 1,
+                iterations: 4,
+                min_ns: 10,
+                median_ns: 25,
+                mean_ns: 25,
+                stddev_ns: 13,
+            }
+        );
+        assert_eq!(
+            BenchStats::from_json_line(&stats.to_json_line()),
+            Some(stats)
+        );
+
+        let single = BenchStats::from_samples(4, 2, &[Duration::from_micros(3)]).unwrap();
+        assert_eq!((single.median_ns, single.stddev_ns), (3000, 0));
+        assert_eq!(BenchStats::from_samples(4, 2, &[]), None);
+    }
+
+    #[test]
+    fn test_bench_part() {
+        let length = |input: &str| Some(input.len().to_string());
+        let stats = bench_part(1, 1, length, "abc", None, 2, 7);
+        assert_eq!(stats.map(|stats| stats.iterations), Ok(7));
+        let stats = bench_part(1, 1, length, "abc", Some("3"), 2, 7);
+        assert_eq!(stats.map(|stats| stats.iterations), Ok(7));
+
+        let wrong = bench_part(1, 1, length, "abc", Some("4"), 2, 7).unwrap_err();
+        assert_eq!(wrong.status, Status::WrongAnswer);
+        let unsolved = bench_part(1, 2, |_| None, "abc", None, 2, 7).unwrap_err();
+        assert_eq!(unsolved.status, Status::Unsolved);
+        let panicked = bench_part(1, 2, |_| panic!("boom"), "abc", None, 2, 7).unwrap_err();
+        assert_eq!(panicked.error.as_deref(), Some("boom"));
+    }
+}
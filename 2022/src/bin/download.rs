@@ -2,21 +2,25 @@
  * This file contains template code.
  * There is no need to edit this file unless you want to change template functionality.
  */
+use aoc2022::runner::{read_input, run_part};
+use aoc2022::{days, read_answers, write_answer, Status};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env::temp_dir, io, process::Command};
 use std::{fs, process};
 
 struct Args {
     day: u8,
     year: Option<i16>,
+    record: bool,
 }
 
 fn parse_args() -> Result<Args, pico_args::Error> {
     let mut args = pico_args::Arguments::from_env();
     Ok(Args {
-        day: args.free_from_str()?,
+        record: args.contains(["-r", "--record"]),
         year: args.opt_value_from_str(["-y", "--year"])?,
+        day: args.free_from_str()?,
     })
 }
 
@@ -32,6 +36,54 @@ fn exit_with_status(status: i32, path: &PathBuf) -> ! {
     process::exit(status);
 }
 
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush().expect("could not flush stdout.");
+    let mut reply = String::new();
+    match io::stdin().read_line(&mut reply) {
+        Ok(_) => matches!(reply.trim().to_lowercase().as_str(), "y" | "yes"),
+        Err(_) => false,
+    }
+}
+
+/// Runs the day's solutions and records each answer the user confirms as correct.
+fn record_answers(day: u8) -> Result<(), String> {
+    let entry = days::find(day)
+        .ok_or_else(|| format!("day {} is not registered in \"src/days.rs\".", day))?;
+    let input = read_input(day)?;
+    let recorded = read_answers(day);
+    let answers_path = format!("src/answers/{:02}.txt", day);
+
+    for (part, solver) in [(1, entry.part_one), (2, entry.part_two)] {
+        let result = run_part(day, part, solver, &input);
+        let answer = match (result.status, &result.answer) {
+            (Status::Solved, Some(answer)) => answer,
+            _ => {
+                println!("Part {}: {}, nothing to record.", part, result);
+                continue;
+            }
+        };
+        let previous = &recorded[usize::from(part - 1)];
+        let prompt = match previous {
+            Some(previous) if previous == answer => {
+                println!("Part {}: {} is already recorded.", part, answer);
+                continue;
+            }
+            Some(previous) => format!(
+                "Part {}: {}\nRecord it as correct (replacing {})?",
+                part, answer, previous
+            ),
+            None => format!("Part {}: {}\nRecord it as correct?", part, answer),
+        };
+        if confirm(&prompt) {
+            write_answer(day, part, answer)
+                .map_err(|e| format!("could not write {}: {}", answers_path, e))?;
+            println!("🎄 Recorded part {} answer in \"{}\".", part, answers_path);
+        }
+    }
+    Ok(())
+}
+
 fn main() {
     // acquire a temp file path to write aoc-cli output to.
     // aoc-cli expects this file not to be present - delete just in case.
@@ -50,6 +102,18 @@ fn main() {
     let day_padded = format!("{:02}", args.day);
     let input_path = format!("src/inputs/{}.txt", day_padded);
 
+    // with `--record`, an input that's already there is used as is
+    if args.record && Path::new(&input_path).exists() {
+        remove_file(&tmp_file_path);
+        match record_answers(args.day) {
+            Ok(()) => process::exit(0),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     // check if aoc binary exists and is callable.
     if Command::new("aoc").arg("-V").output().is_err() {
         eprintln!("command \"aoc\" not found or not callable. Try running \"cargo install aoc-cli\" to install it.");
@@ -95,7 +159,14 @@ fn main() {
         Ok(_) => {
             println!("---");
             println!("🎄 Successfully wrote input to \"{}\".", &input_path);
-            exit_with_status(0, &tmp_file_path);
+            if !args.record {
+                exit_with_status(0, &tmp_file_path);
+            }
+            remove_file(&tmp_file_path);
+            if let Err(e) = record_answers(args.day) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
         }
         Err(e) => {
             eprintln!("could not copy downloaded input to input file: {}", e);
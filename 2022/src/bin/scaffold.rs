@@ -3,7 +3,7 @@
  * There is no need to edit this file unless you want to change template functionality.
  */
 use std::{
-    fs::{File, OpenOptions},
+    fs::{self, File, OpenOptions},
     io::Write,
     process,
 };
@@ -16,30 +16,35 @@ pub fn part_two(input: &str) -> Option<u32> {
     None
 }
 
-fn main() {
-    let input = &aoc2022::read_file("inputs", DAY);
-    aoc2022::solve!(1, part_one, input);
-    aoc2022::solve!(2, part_two, input);
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_part_one() {
-        let input = aoc2022::read_file("examples", DAY);
+        let input = crate::read_file("examples", DAY);
         assert_eq!(part_one(&input), None);
     }
 
     #[test]
     fn test_part_two() {
-        let input = aoc2022::read_file("examples", DAY);
+        let input = crate::read_file("examples", DAY);
         assert_eq!(part_two(&input), None);
     }
 }
 "###;
 
+const BIN_TEMPLATE: &str = r###"use aoc2022::days::dayNN::{part_one, part_two};
+
+fn main() {
+    let input = &aoc2022::read_file("inputs", DAY);
+    aoc2022::solve!(1, part_one, input);
+    aoc2022::solve!(2, part_two, input);
+}
+"###;
+
+const DAYS_PATH: &str = "src/days.rs";
+
 fn parse_args() -> Result<u8, pico_args::Error> {
     let mut args = pico_args::Arguments::from_env();
     args.free_from_str()
@@ -50,7 +55,33 @@ fn safe_create_file(path: &str) -> Result<File, std::io::Error> {
 }
 
 fn create_file(path: &str) -> Result<File, std::io::Error> {
-    OpenOptions::new().write(true).create(true).open(path)
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Adds `pub mod dayNN;` after the last day module in `src/days.rs` and `day!(N, dayNN),`
+/// at the end of the `DAYS` table. `None` if the file doesn't have the expected shape.
+fn register_day(days: &str, day: u8) -> Option<String> {
+    let module = format!("day{:02}", day);
+    let declaration = format!("pub mod {};\n", module);
+    if days.contains(&declaration) {
+        return Some(days.to_string());
+    }
+    let last_module = days.rfind("pub mod day")?;
+    let after_modules = last_module + days[last_module..].find('\n')? + 1;
+    let table_end = days.find("\n];")? + 1;
+    Some(format!(
+        "{}{}{}    day!({}, {}),\n{}",
+        &days[..after_modules],
+        declaration,
+        &days[after_modules..table_end],
+        day,
+        module,
+        &days[table_end..]
+    ))
 }
 
 fn main() {
@@ -66,22 +97,43 @@ fn main() {
 
     let input_path = format!("src/inputs/{}.txt", day_padded);
     let example_path = format!("src/examples/{}.txt", day_padded);
-    let module_path = format!("src/bin/{}.rs", day_padded);
-
-    let mut file = match safe_create_file(&module_path) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Failed to create module file: {}", e);
-            process::exit(1);
+    let module_path = format!("src/days/day{}.rs", day_padded);
+    let bin_path = format!("src/bin/{}.rs", day_padded);
+
+    for (path, template) in [(&module_path, MODULE_TEMPLATE), (&bin_path, BIN_TEMPLATE)] {
+        let mut file = match safe_create_file(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to create module file: {}", e);
+                process::exit(1);
+            }
+        };
+
+        let contents = template
+            .replace("NN", &day_padded)
+            .replace("DAY", &day.to_string());
+        match file.write_all(contents.as_bytes()) {
+            Ok(_) => {
+                println!("Created module file \"{}\"", path);
+            }
+            Err(e) => {
+                eprintln!("Failed to write module contents: {}", e);
+                process::exit(1);
+            }
         }
-    };
+    }
 
-    match file.write_all(MODULE_TEMPLATE.replace("DAY", &day.to_string()).as_bytes()) {
-        Ok(_) => {
-            println!("Created module file \"{}\"", &module_path);
-        }
-        Err(e) => {
-            eprintln!("Failed to write module contents: {}", e);
+    let registered = fs::read_to_string(DAYS_PATH)
+        .ok()
+        .and_then(|days| register_day(&days, day))
+        .map(|days| fs::write(DAYS_PATH, days));
+    match registered {
+        Some(Ok(_)) => println!("Registered day {} in \"{}\"", day, DAYS_PATH),
+        _ => {
+            eprintln!(
+                "Failed to register the day: add `pub mod day{}` and `day!({}, day{})` to \"{}\"",
+                day_padded, day, day_padded, DAYS_PATH
+            );
             process::exit(1);
         }
     }
@@ -0,0 +1,58 @@
+/*
+ * Register a day here (module + table entry) to include it in `cargo all`.
+ * Each day's solution lives in `days/dayNN.rs`; `src/bin/NN.rs` runs it for `cargo solve NN`.
+ */
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+
+/// A part's solution with its answer rendered as text.
+pub type Solver = fn(&str) -> Option<String>;
+
+pub struct Day {
+    pub day: u8,
+    pub part_one: Solver,
+    pub part_two: Solver,
+}
+
+macro_rules! day {
+    ($day:expr, $module:ident) => {
+        Day {
+            day: $day,
+            part_one: |input| $module::part_one(input).map(|answer| answer.to_string()),
+            part_two: |input| $module::part_two(input).map(|answer| answer.to_string()),
+        }
+    };
+}
+
+pub const DAYS: &[Day] = &[
+    day!(1, day01),
+    day!(2, day02),
+    day!(3, day03),
+    day!(4, day04),
+    day!(5, day05),
+    day!(6, day06),
+    day!(7, day07),
+    day!(8, day08),
+    day!(9, day09),
+    day!(10, day10),
+    day!(11, day11),
+    day!(12, day12),
+    day!(13, day13),
+    day!(14, day14),
+];
+
+pub fn find(day: u8) -> Option<&'static Day> {
+    DAYS.iter().find(|entry| entry.day == day)
+}
@@ -0,0 +1,37 @@
+use std::collections::HashSet;
+
+fn find_first_preamble_index(input: &str, size: usize) -> Option<u32> {
+    for (i, candidate) in input.chars().collect::<Vec<_>>().windows(size).enumerate() {
+        let set: HashSet<&char> = HashSet::from_iter(candidate.iter());
+        if set.len() == size {
+            return Some((i + size) as u32);
+        }
+    }
+
+    None
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    find_first_preamble_index(input, 4)
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    find_first_preamble_index(input, 14)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_one() {
+        let input = crate::read_file("examples", 6);
+        assert_eq!(part_one(&input), Some(11));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let input = crate::read_file("examples", 6);
+        assert_eq!(part_two(&input), Some(26));
+    }
+}
@@ -0,0 +1,271 @@
+use aoc_vm::{Step, Vm};
+use std::convert::Infallible;
+
+#[derive(Debug)]
+enum Instruction {
+    NoOp,
+    AddX(isize),
+}
+
+const CRT_COLS: usize = 40;
+
+struct WalkieVM {
+    program: Vec<Instruction>,
+    register_x: isize,
+    tick: usize,
+    instruction_pointer: usize,
+    complete_at: Option<usize>,
+}
+
+impl WalkieVM {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            program,
+            register_x: 1,
+            tick: 0,
+            instruction_pointer: 0,
+            complete_at: None,
+        }
+    }
+}
+
+struct WalkieVMState {
+    tick: usize,
+    register_x: isize,
+}
+
+impl Iterator for WalkieVM {
+    type Item = WalkieVMState;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(Step::Halted) => None,
+            // the cycle that finishes the last instruction is already past the program
+            Ok(_) if self.is_halted() => None,
+            Ok(_) => Some(WalkieVMState {
+                tick: self.tick,
+                register_x: self.register_x,
+            }),
+            Err(never) => match never {},
+        }
+    }
+}
+
+/// Each step is one clock tick, so an `addx` takes two.
+impl Vm for WalkieVM {
+    type Error = Infallible;
+    type Snapshot = (usize, isize, usize, Option<usize>);
+
+    fn step(&mut self) -> Result<Step, Infallible> {
+        if self.is_halted() {
+            return Ok(Step::Halted);
+        }
+        match (self.complete_at, self.program.get(self.instruction_pointer)) {
+            (None, _) => {
+                // First cycle
+                self.complete_at =
+                    get_instruction_length(self.program.get(self.instruction_pointer));
+            }
+            (Some(t), Some(current_instruction)) if t == self.tick => {
+                self.instruction_pointer += 1;
+
+                if let Instruction::AddX(x) = current_instruction {
+                    self.register_x += x;
+                };
+
+                // Determine when the next instruction will complete; past the last
+                // one, `is_halted` turns true and the next step reports it
+                if let Some(length) =
+                    get_instruction_length(self.program.get(self.instruction_pointer))
+                {
+                    self.complete_at = Some(self.tick + length);
+                }
+            }
+            _ => (/* clock tick with no state change */),
+        }
+
+        self.tick += 1;
+        Ok(Step::Executed)
+    }
+
+    fn ip(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    fn is_halted(&self) -> bool {
+        self.complete_at.is_some() && self.instruction_pointer >= self.program.len()
+    }
+
+    fn registers(&self) -> Vec<(&'static str, i64)> {
+        vec![("x", self.register_x as i64), ("tick", self.tick as i64)]
+    }
+
+    fn memory_len(&self) -> usize {
+        self.program.len()
+    }
+
+    fn instruction(&self, address: usize) -> Option<(String, usize)> {
+        let text = match self.program.get(address)? {
+            Instruction::NoOp => "noop".to_string(),
+            Instruction::AddX(x) => format!("addx {}", x),
+        };
+        Some((text, 1))
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        (
+            self.instruction_pointer,
+            self.register_x,
+            self.tick,
+            self.complete_at,
+        )
+    }
+
+    fn restore(&mut self, snapshot: &Self::Snapshot) {
+        (
+            self.instruction_pointer,
+            self.register_x,
+            self.tick,
+            self.complete_at,
+        ) = *snapshot;
+    }
+}
+
+fn parse_input(input: &str) -> Vec<Instruction> {
+    input
+        .lines()
+        .map(|line| {
+            // Parse a line like "noop" into NoOp
+            let mut parts = line.split_whitespace();
+            let instruction = parts.next().unwrap();
+
+            match instruction {
+                "noop" => Instruction::NoOp,
+                "addx" => Instruction::AddX(parts.next().unwrap().parse().unwrap()),
+                _ => panic!("Invalid instruction"),
+            }
+        })
+        .collect()
+}
+
+fn get_instruction_length(instruction: Option<&Instruction>) -> Option<usize> {
+    match instruction {
+        Some(Instruction::NoOp) => Some(1),
+        Some(Instruction::AddX(_)) => Some(2),
+        _ => None,
+    }
+}
+
+pub fn part_one(input: &str) -> Option<isize> {
+    let instructions = parse_input(input);
+    let vm = WalkieVM::new(instructions);
+
+    let mut signal_strength: isize = 0;
+
+    for state in vm {
+        if state.tick == 20 || (((state.tick as isize) - 20) % 40) == 0 {
+            signal_strength += state.register_x * (state.tick as isize);
+        }
+    }
+
+    Some(signal_strength)
+}
+
+#[derive(Debug, Clone)]
+enum CRTPixel {
+    Lit,
+    Dark,
+}
+
+pub fn part_two(input: &str) -> Option<String> {
+    let program = parse_input(input);
+    let vm = WalkieVM::new(program);
+    let mut crt_buffer: Vec<CRTPixel> = vec![];
+
+    for state in vm {
+        let drawing_pixel = (state.tick - 1) % CRT_COLS;
+        let sprite_location = state.register_x;
+        let is_lit = (drawing_pixel as isize - sprite_location).abs() <= 1;
+        match is_lit {
+            true => crt_buffer.push(CRTPixel::Lit),
+            false => crt_buffer.push(CRTPixel::Dark),
+        };
+    }
+
+    let display = crt_buffer
+        .chunks(CRT_COLS)
+        .map(|row| {
+            row.iter()
+                .map(|pixel| match pixel {
+                    CRTPixel::Lit => "#",
+                    CRTPixel::Dark => ".",
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>();
+
+    Some(display.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_one() {
+        let input = crate::read_file("examples", 10);
+        assert_eq!(part_one(&input), Some(13140));
+    }
+
+    #[test]
+    fn test_shared_tooling() {
+        let program = parse_input("noop\naddx 3\naddx -5");
+        let mut lines = Vec::new();
+        let mut vm = WalkieVM::new(program);
+        let stop = aoc_vm::trace::trace(&mut vm, 100, |line| lines.push(line.to_string()));
+        assert_eq!(stop, Ok(Step::Halted));
+        assert_eq!(
+            lines,
+            [
+                "     0  noop                     x=1 tick=1",
+                "     0  noop                     x=1 tick=2",
+                "     1  addx 3                   x=1 tick=3",
+                "     1  addx 3                   x=4 tick=4",
+                "     2  addx -5                  x=4 tick=5",
+                "     2  addx -5                  x=-1 tick=6",
+            ]
+        );
+        assert!(vm.is_halted());
+        assert_eq!(vm.register_x, -1);
+
+        let mut debugger = aoc_vm::debugger::Debugger::new(WalkieVM::new(parse_input(
+            "noop\naddx 3\naddx -5",
+        )));
+        debugger.add_breakpoint(2);
+        assert_eq!(
+            debugger.execute("c"),
+            Ok("breakpoint at 2\n     2  addx -5".to_string())
+        );
+        assert_eq!(debugger.execute("regs"), Ok("ip=2 x=4 tick=4".to_string()));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let input = crate::read_file("examples", 10);
+        assert_eq!(
+            part_two(&input),
+            Some(
+                "
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+"
+                .trim()
+                .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,123 @@
+extern crate serde;
+extern crate serde_json;
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone, Eq, PartialEq)]
+#[serde(untagged)]
+enum Node {
+    Number(u64),
+    List(Vec<Node>),
+}
+
+impl Node {
+    fn with_slice<T>(&self, f: impl FnOnce(&[Node]) -> T) -> T {
+        match self {
+            Self::Number(n) => f(&[Self::Number(*n)]),
+            Self::List(l) => f(l),
+        }
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Number(l), Self::Number(r)) => l.cmp(r),
+            (l, r) => l.with_slice(|l_as_list| {
+                r.with_slice(|r_as_list| {
+                    l_as_list
+                        .iter()
+                        .zip(r_as_list)
+                        .map(|(l_el, r_el)| l_el.cmp(r_el))
+                        .find(|o| o != &Ordering::Equal)
+                        .unwrap_or_else(|| l_as_list.len().cmp(&r_as_list.len()))
+                })
+            }),
+        }
+    }
+}
+
+fn parse_input(input: &str) -> Vec<(Vec<Node>, Vec<Node>)> {
+    input
+        .split("\n\n")
+        .map(|transmission| {
+            let packets = transmission
+                .lines()
+                .map(|line| serde_json::from_str::<Node>(line).unwrap())
+                .map(|node| match node {
+                    Node::List(l) => l,
+                    _ => unreachable!("Bad parse: should be a top level list"),
+                })
+                .collect::<Vec<_>>();
+
+            (packets[0].clone(), packets[1].clone())
+        })
+        .collect::<Vec<_>>()
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let packet_groups = parse_input(input);
+    let index_sum = packet_groups
+        .into_iter()
+        .enumerate()
+        .fold(0, |acc, (i, (left, right))| match left < right {
+            true => acc + i as u32 + 1,
+            false => acc,
+        });
+
+    Some(index_sum)
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    let mut all_packets =
+        parse_input(input)
+            .into_iter()
+            .fold(Vec::new(), |mut acc, (left, right)| {
+                acc.push(left);
+                acc.push(right);
+                acc
+            });
+    all_packets.sort();
+
+    let divider_one = vec![Node::List(vec![Node::Number(2)])];
+    let divider_two = vec![Node::List(vec![Node::Number(6)])];
+
+    let divider_one_index = all_packets
+        .iter()
+        .position(|packet| packet > &divider_one)
+        .unwrap()
+        + 1;
+
+    let divider_two_index = all_packets
+        .iter()
+        .position(|packet| packet > &divider_two)
+        .unwrap()
+        + 2;
+
+    Some(divider_one_index as u32 * divider_two_index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_one() {
+        let input = crate::read_file("examples", 13);
+        assert_eq!(part_one(&input), Some(13));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let input = crate::read_file("examples", 13);
+        assert_eq!(part_two(&input), Some(140));
+    }
+}
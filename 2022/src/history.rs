@@ -0,0 +1,397 @@
+/*
+ * This file contains template code.
+ * There is no need to edit this file unless you want to change template functionality.
+ */
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where `cargo all` appends a record of each run, relative to the crate root.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Each part ran once, so its median is that one time.
+    Run,
+    Bench,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    pub day: u8,
+    pub part: u8,
+    pub median_ns: u64,
+}
+
+/// One line of the history file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Short hash of `HEAD`, with `-dirty` if the tree had changes.
+    pub commit: String,
+    /// UTC, e.g. `2022-12-14T09:30:00Z`.
+    pub date: String,
+    pub mode: Mode,
+    /// Set by `--save-baseline`, so later runs can be compared against this one by name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<String>,
+    pub timings: Vec<Timing>,
+}
+
+impl Run {
+    /// A record of the current commit at the current time.
+    pub fn new(mode: Mode, baseline: Option<String>, timings: Vec<Timing>) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Run {
+            commit: current_commit(),
+            date: format_date(now),
+            mode,
+            baseline,
+            timings,
+        }
+    }
+
+    /// Each part's median, by `(day, part)`.
+    pub fn part_times(&self) -> BTreeMap<(u8, u8), u64> {
+        self.timings
+            .iter()
+            .map(|timing| ((timing.day, timing.part), timing.median_ns))
+            .collect()
+    }
+
+    /// `96fd415 2022-12-14T09:30:00Z (bench, baseline "v1")`
+    pub fn describe(&self) -> String {
+        let mode = match self.mode {
+            Mode::Run => "run",
+            Mode::Bench => "bench",
+        };
+        match &self.baseline {
+            Some(name) => format!(
+                "{} {} ({}, baseline \"{}\")",
+                self.commit, self.date, mode, name
+            ),
+            None => format!("{} {} ({})", self.commit, self.date, mode),
+        }
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    match output.status.success() {
+        true => String::from_utf8(output.stdout).ok(),
+        false => None,
+    }
+}
+
+fn current_commit() -> String {
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(hash) => {
+            let dirty = git(&["status", "--porcelain"]).is_some_and(|out| !out.is_empty());
+            format!("{}{}", hash.trim(), if dirty { "-dirty" } else { "" })
+        }
+        None => "unknown".to_string(),
+    }
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+/// Days to civil date from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn format_date(secs: u64) -> String {
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+pub fn append(path: &Path, run: &Run) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(run).expect("runs always serialize");
+    writeln!(file, "{}", line)
+}
+
+/// All recorded runs, oldest first. A missing file is an empty history; lines that
+/// don't parse are skipped.
+pub fn load(path: &Path) -> io::Result<Vec<Run>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Picks the two runs to compare, as `(before, after)`. Without a `baseline` these are
+/// the latest run and the one before it in the same mode; with one, the newest run saved
+/// under that name and the latest run after it in the same mode.
+pub fn select<'a>(runs: &'a [Run], baseline: Option<&str>) -> Result<(&'a Run, &'a Run), String> {
+    let (before, after) = match baseline {
+        Some(name) => {
+            let index = runs
+                .iter()
+                .rposition(|run| run.baseline.as_deref() == Some(name))
+                .ok_or_else(|| format!("no baseline named \"{}\"", name))?;
+            let before = &runs[index];
+            let after = runs[index + 1..]
+                .iter()
+                .rev()
+                .find(|run| run.mode == before.mode);
+            (Some(before), after)
+        }
+        None => {
+            let (after, earlier) = runs.split_last().ok_or("no runs recorded yet")?;
+            let before = earlier.iter().rev().find(|run| run.mode == after.mode);
+            (before, Some(after))
+        }
+    };
+    match (before, after) {
+        (Some(before), Some(after)) => Ok((before, after)),
+        _ => Err("need at least two runs in the same mode to compare".into()),
+    }
+}
+
+/// How one part's time moved between two runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub day: u8,
+    pub part: u8,
+    pub before_ns: u64,
+    pub after_ns: u64,
+    /// Percentage change, positive when slower.
+    pub change: f64,
+    /// Slower by more than the threshold.
+    pub regressed: bool,
+}
+
+/// How the parts timed in the earlier run fared in the later one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub changes: Vec<Change>,
+    /// Parts timed before but not after, as `(day, part)`. Only solved parts are timed,
+    /// so these panicked, returned `None` or gave a wrong answer.
+    pub missing: Vec<(u8, u8)>,
+}
+
+impl Comparison {
+    /// Whether any part regressed or went missing.
+    pub fn failed(&self) -> bool {
+        !self.missing.is_empty() || self.changes.iter().any(|change| change.regressed)
+    }
+}
+
+/// Compares every part timed in `before` with the same part in `after`. `threshold` is
+/// a percentage. Parts only timed in `after` are new and have nothing to compare with.
+pub fn compare(before: &Run, after: &Run, threshold: f64) -> Comparison {
+    let after_times = after.part_times();
+    let mut comparison = Comparison {
+        changes: Vec::new(),
+        missing: Vec::new(),
+    };
+    for ((day, part), before_ns) in before.part_times() {
+        let after_ns = match after_times.get(&(day, part)) {
+            Some(after_ns) => *after_ns,
+            None => {
+                comparison.missing.push((day, part));
+                continue;
+            }
+        };
+        let change = match before_ns {
+            0 => 0.0,
+            _ => (after_ns as f64 - before_ns as f64) / before_ns as f64 * 100.0,
+        };
+        comparison.changes.push(Change {
+            day,
+            part,
+            before_ns,
+            after_ns,
+            change,
+            regressed: change > threshold,
+        });
+    }
+    comparison
+}
+
+/// `Day 01 part 1     8.51µs ->     9.80µs    +15.2%  regressed`
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let duration = |ns| format!("{:.2?}", std::time::Duration::from_nanos(ns));
+        write!(
+            f,
+            "Day {:02} part {} {:>10} -> {:>10} {:>+8.1}%",
+            self.day,
+            self.part,
+            duration(self.before_ns),
+            duration(self.after_ns),
+            self.change
+        )?;
+        if self.regressed {
+            write!(f, "  regressed")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(mode: Mode, baseline: Option<&str>, timings: &[(u8, u8, u64)]) -> Run {
+        Run {
+            commit: "abc1234".into(),
+            date: "2022-12-14T09:30:00Z".into(),
+            mode,
+            baseline: baseline.map(String::from),
+            timings: timings
+                .iter()
+                .map(|&(day, part, median_ns)| Timing {
+                    day,
+                    part,
+                    median_ns,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_date(1_671_010_200), "2022-12-14T09:30:00Z");
+        assert_eq!(format_date(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_select() {
+        let runs = vec![
+            run(Mode::Bench, Some("v1"), &[]),
+            run(Mode::Bench, None, &[]),
+            run(Mode::Run, None, &[]),
+            run(Mode::Bench, None, &[(1, 1, 5)]),
+        ];
+        let (before, after) = select(&runs, None).unwrap();
+        assert!(std::ptr::eq(before, &runs[1]));
+        assert!(std::ptr::eq(after, &runs[3]));
+        let (before, after) = select(&runs, Some("v1")).unwrap();
+        assert!(std::ptr::eq(before, &runs[0]));
+        assert!(std::ptr::eq(after, &runs[3]));
+        assert_eq!(
+            select(&runs[..3], Some("v1")).map(|(_, after)| after),
+            Ok(&runs[1])
+        );
+
+        assert_eq!(
+            select(&runs, Some("v2")),
+            Err("no baseline named \"v2\"".into())
+        );
+        assert_eq!(
+            select(&runs[2..3], None).unwrap_err(),
+            "need at least two runs in the same mode to compare"
+        );
+        assert_eq!(
+            select(&runs[..1], Some("v1")).unwrap_err(),
+            "need at least two runs in the same mode to compare"
+        );
+        assert_eq!(select(&[], None).unwrap_err(), "no runs recorded yet");
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = run(
+            Mode::Bench,
+            None,
+            &[(1, 1, 1000), (1, 2, 1000), (2, 1, 500), (3, 1, 7)],
+        );
+        let after = run(
+            Mode::Bench,
+            None,
+            &[(1, 1, 1100), (1, 2, 1150), (2, 1, 400), (4, 1, 7)],
+        );
+        let comparison = compare(&before, &after, 12.0);
+        assert_eq!(
+            comparison.changes,
+            [
+                Change {
+                    day: 1,
+                    part: 1,
+                    before_ns: 1000,
+                    after_ns: 1100,
+                    change: 10.0,
+                    regressed: false,
+                },
+                Change {
+                    day: 1,
+                    part: 2,
+                    before_ns: 1000,
+                    after_ns: 1150,
+                    change: 15.0,
+                    regressed: true,
+                },
+                Change {
+                    day: 2,
+                    part: 1,
+                    before_ns: 500,
+                    after_ns: 400,
+                    change: -20.0,
+                    regressed: false,
+                },
+            ]
+        );
+        assert_eq!(comparison.missing, [(3, 1)]);
+        assert!(comparison.failed());
+        assert_eq!(
+            comparison.changes[1].to_string(),
+            "Day 01 part 2     1.00µs ->     1.15µs    +15.0%  regressed"
+        );
+
+        // a part that stops being timed, e.g. because it panicked, can't make its day
+        // look faster
+        let after = run(Mode::Bench, None, &[(1, 1, 1000), (2, 1, 500), (3, 1, 7)]);
+        let comparison = compare(&before, &after, 10.0);
+        assert!(comparison.changes.iter().all(|change| !change.regressed));
+        assert_eq!(comparison.missing, [(1, 2)]);
+        assert!(comparison.failed());
+
+        let comparison = compare(&before, &before, 10.0);
+        assert!(comparison.missing.is_empty());
+        assert!(!comparison.failed());
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("aoc2022-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(load(&path).unwrap(), []);
+
+        let first = run(Mode::Run, Some("v1"), &[(1, 1, 42)]);
+        let second = run(Mode::Bench, None, &[(1, 1, 40)]);
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+        assert_eq!(load(&path).unwrap(), [first, second]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,44 +3,178 @@
  * There is no need to edit this file unless you want to change template functionality.
  * Prefer `./helpers.rs` if you want to extract code from your solutions.
  */
+use serde::{Deserialize, Serialize};
 use std::env;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
+pub mod bench;
+pub mod days;
 pub mod helpers;
+pub mod history;
+pub mod runner;
 
 pub const ANSI_ITALIC: &str = "\x1b[3m";
 pub const ANSI_BOLD: &str = "\x1b[1m";
 pub const ANSI_RESET: &str = "\x1b[0m";
 
+/// When set (to anything but `0`), `solve!` prints one JSON line per part instead of text.
+pub const JSON_ENV: &str = "AOC_JSON";
+
 #[macro_export]
 macro_rules! solve {
     ($part:expr, $solver:ident, $input:expr) => {{
-        use aoc2022::{ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
+        use aoc2022::{PartResult, ANSI_BOLD, ANSI_RESET};
         use std::fmt::Display;
         use std::time::Instant;
 
-        fn print_result<T: Display>(func: impl FnOnce(&str) -> Option<T>, input: &str) {
+        fn print_result<T: Display>(part: u8, func: impl FnOnce(&str) -> Option<T>, input: &str) {
             let timer = Instant::now();
             let result = func(input);
             let elapsed = timer.elapsed();
-            match result {
-                Some(result) => {
-                    println!(
-                        "{} {}(elapsed: {:.2?}){}",
-                        result, ANSI_ITALIC, elapsed, ANSI_RESET
-                    );
-                }
-                None => {
-                    println!("not solved.")
-                }
+            let day = env!("CARGO_BIN_NAME").parse().unwrap_or(0);
+            let answer = result.map(|result| result.to_string());
+            let result = PartResult::new(day, part, answer, elapsed);
+            match aoc2022::json_output() {
+                true => println!("{}", result.to_json_line()),
+                false => println!("{}", result),
             }
         }
 
-        println!("🎄 {}Part {}{} 🎄", ANSI_BOLD, $part, ANSI_RESET);
-        print_result($solver, $input);
+        if !aoc2022::json_output() {
+            println!("🎄 {}Part {}{} 🎄", ANSI_BOLD, $part, ANSI_RESET);
+        }
+        print_result($part, $solver, $input);
     }};
 }
 
+/// Whether `solve!` should print JSON lines: `AOC_JSON` is set, or the binary was run
+/// with `--json`.
+pub fn json_output() -> bool {
+    let from_env = matches!(env::var(JSON_ENV), Ok(value) if value != "0");
+    from_env || env::args().any(|arg| arg == "--json")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Solved,
+    /// The part returned `None`.
+    Unsolved,
+    /// The day is not registered in `days::DAYS`.
+    NotImplemented,
+    Panicked,
+    /// The answer differs from the one recorded in `src/answers`.
+    WrongAnswer,
+}
+
+/// The outcome of one part, as printed by `solve!` in JSON mode:
+/// `{"day":1,"part":2,"answer":"45000","duration_ns":37030,"status":"solved"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartResult {
+    pub day: u8,
+    pub part: u8,
+    pub answer: Option<String>,
+    pub duration_ns: u64,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// The panic message of a `Panicked` part.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PartResult {
+    pub fn new(day: u8, part: u8, answer: Option<String>, elapsed: Duration) -> Self {
+        let status = match answer {
+            Some(_) => Status::Solved,
+            None => Status::Unsolved,
+        };
+        PartResult {
+            day,
+            part,
+            answer,
+            duration_ns: elapsed.as_nanos() as u64,
+            status,
+            expected: None,
+            error: None,
+        }
+    }
+
+    pub fn not_implemented(day: u8, part: u8) -> Self {
+        PartResult {
+            status: Status::NotImplemented,
+            ..PartResult::new(day, part, None, Duration::ZERO)
+        }
+    }
+
+    pub fn panicked(day: u8, part: u8, message: String, elapsed: Duration) -> Self {
+        PartResult {
+            status: Status::Panicked,
+            error: Some(message),
+            ..PartResult::new(day, part, None, elapsed)
+        }
+    }
+
+    /// Marks a solved part as `WrongAnswer` if it doesn't match `expected`.
+    pub fn check(mut self, expected: Option<&str>) -> Self {
+        if let (Some(answer), Some(expected)) = (&self.answer, expected) {
+            if answer != expected {
+                self.status = Status::WrongAnswer;
+            }
+        }
+        self.expected = expected.map(String::from);
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.duration_ns)
+    }
+
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("results always serialize")
+    }
+
+    /// Parses a line printed by `solve!` in JSON mode; anything else (such as a
+    /// solution's own debug output) gives `None`.
+    pub fn from_json_line(line: &str) -> Option<Self> {
+        serde_json::from_str(line.trim()).ok()
+    }
+}
+
+/// The human-readable result line, e.g. `6 (elapsed: 37.03µs)`.
+impl fmt::Display for PartResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.status, &self.answer) {
+            (Status::NotImplemented, _) => write!(f, "not implemented."),
+            (Status::Panicked, _) => {
+                let message = self.error.as_deref().unwrap_or("unknown error");
+                write!(f, "panicked: {}", message)
+            }
+            (_, Some(answer)) => {
+                write!(
+                    f,
+                    "{} {}(elapsed: {:.2?}){}",
+                    answer,
+                    ANSI_ITALIC,
+                    self.elapsed(),
+                    ANSI_RESET
+                )?;
+                match (self.status, &self.expected) {
+                    (Status::WrongAnswer, Some(expected)) => {
+                        write!(f, " wrong answer, expected {}", expected)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (_, None) => write!(f, "not solved."),
+        }
+    }
+}
+
 pub fn read_file(folder: &str, day: u8) -> String {
     read_file_custom(folder, day, None)
 }
@@ -57,45 +191,93 @@ pub fn read_file_custom(folder: &str, day: u8, suffix: Option<&str>) -> String {
     f.expect("could not open input file")
 }
 
-fn parse_time(val: &str, postfix: &str) -> f64 {
-    val.split(postfix).next().unwrap().parse().unwrap()
-}
-
-pub fn parse_exec_time(output: &str) -> f64 {
-    output.lines().fold(0_f64, |acc, l| {
-        if !l.contains("elapsed:") {
-            acc
-        } else {
-            let timing = l.split("(elapsed: ").last().unwrap();
-            // use `contains` istd. of `ends_with`: string may contain ANSI escape sequences.
-            // for possible time formats, see: https://github.com/rust-lang/rust/blob/1.64.0/library/core/src/time.rs#L1176-L1200
-            if timing.contains("ns)") {
-                acc // range below rounding precision.
-            } else if timing.contains("µs)") {
-                acc + parse_time(timing, "µs") / 1000_f64
-            } else if timing.contains("ms)") {
-                acc + parse_time(timing, "ms")
-            } else if timing.contains("s)") {
-                acc + parse_time(timing, "s") * 1000_f64
-            } else {
-                acc
-            }
+fn answers_path(day: u8) -> PathBuf {
+    let cwd = env::current_dir().unwrap();
+    cwd.join("src")
+        .join("answers")
+        .join(format!("{:02}.txt", day))
+}
+
+/// The recorded answers for a day from `src/answers/NN.txt`: part one on the first line,
+/// part two on the second. A missing file or blank line means the answer isn't known.
+/// Multi-line answers, like day 10's CRT picture, are stored with `\n` escapes.
+pub fn read_answers(day: u8) -> [Option<String>; 2] {
+    let contents = fs::read_to_string(answers_path(day)).unwrap_or_default();
+    parse_answers(&contents)
+}
+
+/// Records the answer to one part in `src/answers/NN.txt`, keeping the other part's.
+/// Errors without writing if the answer is empty or starts or ends with whitespace,
+/// since it wouldn't read back the same.
+pub fn write_answer(day: u8, part: u8, answer: &str) -> io::Result<()> {
+    if answer.is_empty() || answer.trim() != answer {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "can't record {:?}: answers must not be empty or start or end with whitespace",
+                answer
+            ),
+        ));
+    }
+    let mut answers = read_answers(day);
+    answers[usize::from(part == 2)] = Some(answer.to_string());
+    let path = answers_path(day);
+    fs::create_dir_all(path.parent().expect("answers live in a directory"))?;
+    fs::write(path, format_answers(&answers))
+}
+
+fn parse_answers(contents: &str) -> [Option<String>; 2] {
+    let mut lines = contents.lines().map(str::trim);
+    let mut next = || lines.next().filter(|line| !line.is_empty()).map(unescape);
+    [next(), next()]
+}
+
+fn format_answers(answers: &[Option<String>; 2]) -> String {
+    answers
+        .iter()
+        .map(|answer| match answer {
+            Some(answer) => format!("{}\n", escape(answer)),
+            None => "\n".to_string(),
+        })
+        .collect()
+}
+
+/// Puts an answer on one line: `\n`, `\r` and `\\` for line breaks and backslashes.
+fn escape(answer: &str) -> String {
+    let mut escaped = String::with_capacity(answer.len());
+    for c in answer.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
         }
-    })
+    }
+    escaped
 }
 
-/// copied from: https://github.com/rust-lang/rust/blob/1.64.0/library/std/src/macros.rs#L328-L333
-#[cfg(test)]
-macro_rules! assert_approx_eq {
-    ($a:expr, $b:expr) => {{
-        let (a, b) = (&$a, &$b);
-        assert!(
-            (*a - *b).abs() < 1.0e-6,
-            "{} is not approximately equal to {}",
-            *a,
-            *b
-        );
-    }};
+/// Reverses `escape`. A backslash before anything else is kept as is, so hand-written
+/// answers with a stray backslash still read back.
+fn unescape(line: &str) -> String {
+    let mut answer = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            answer.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => answer.push('\n'),
+            Some('r') => answer.push('\r'),
+            Some('\\') => answer.push('\\'),
+            Some(other) => {
+                answer.push('\\');
+                answer.push(other);
+            }
+            None => answer.push('\\'),
+        }
+    }
+    answer
 }
 
 #[cfg(test)]
@@ -103,30 +285,79 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_exec_time() {
-        assert_approx_eq!(
-            parse_exec_time(&format!(
-                "🎄 Part 1 🎄\n0 (elapsed: 74.13ns){}\n🎄 Part 2 🎄\n0 (elapsed: 50.00ns){}",
-                ANSI_RESET, ANSI_RESET
-            )),
-            0_f64
+    fn test_part_result_json() {
+        let result = PartResult::new(1, 2, Some("45000".into()), Duration::from_nanos(37030));
+        let line = result.to_json_line();
+        assert_eq!(
+            line,
+            r#"{"day":1,"part":2,"answer":"45000","duration_ns":37030,"status":"solved"}"#
+        );
+        assert_eq!(PartResult::from_json_line(&line), Some(result.clone()));
+        assert_eq!(PartResult::from_json_line("debug: 45000"), None);
+        assert_eq!(
+            result.to_string(),
+            format!("45000 {}(elapsed: 37.03µs){}", ANSI_ITALIC, ANSI_RESET)
         );
 
-        assert_approx_eq!(
-            parse_exec_time("🎄 Part 1 🎄\n0 (elapsed: 755µs)\n🎄 Part 2 🎄\n0 (elapsed: 700µs)"),
-            1.455_f64
+        let unsolved = PartResult::new(3, 1, None, Duration::from_millis(2));
+        assert_eq!(unsolved.status, Status::Unsolved);
+        assert_eq!(unsolved.to_string(), "not solved.");
+        assert_eq!(
+            PartResult::from_json_line(&unsolved.to_json_line()),
+            Some(unsolved)
         );
+    }
+
+    #[test]
+    fn test_part_result_status() {
+        let elapsed = Duration::from_nanos(1500);
+        let right = PartResult::new(2, 1, Some("15".into()), elapsed).check(Some("15"));
+        assert_eq!(right.status, Status::Solved);
 
-        assert_approx_eq!(
-            parse_exec_time("🎄 Part 1 🎄\n0 (elapsed: 70µs)\n🎄 Part 2 🎄\n0 (elapsed: 1.45ms)"),
-            1.52_f64
+        let wrong = PartResult::new(2, 1, Some("14".into()), elapsed).check(Some("15"));
+        assert_eq!(wrong.status, Status::WrongAnswer);
+        assert_eq!(
+            wrong.to_string(),
+            format!(
+                "14 {}(elapsed: 1.50µs){} wrong answer, expected 15",
+                ANSI_ITALIC, ANSI_RESET
+            )
+        );
+        assert_eq!(
+            wrong.to_json_line(),
+            r#"{"day":2,"part":1,"answer":"14","duration_ns":1500,"status":"wrong_answer","expected":"15"}"#
         );
 
-        assert_approx_eq!(
-            parse_exec_time(
-                "🎄 Part 1 🎄\n0 (elapsed: 10.3s)\n🎄 Part 2 🎄\n0 (elapsed: 100.50ms)"
-            ),
-            10400.50_f64
+        let unknown = PartResult::new(2, 2, Some("12".into()), elapsed).check(None);
+        assert_eq!(unknown.status, Status::Solved);
+
+        let panicked = PartResult::panicked(3, 1, "boom".into(), elapsed);
+        assert_eq!(panicked.to_string(), "panicked: boom");
+        assert_eq!(
+            PartResult::not_implemented(25, 2).to_string(),
+            "not implemented."
         );
     }
+
+    #[test]
+    fn test_answers_file() {
+        assert_eq!(parse_answers(""), [None, None]);
+        assert_eq!(parse_answers("24000\n"), [Some("24000".into()), None]);
+        assert_eq!(parse_answers("\nCMZ\n"), [None, Some("CMZ".into())]);
+        assert_eq!(format_answers(&[None, Some("CMZ".into())]), "\nCMZ\n");
+        let answers = [Some("24000".into()), Some("##..\n..##".into())];
+        assert_eq!(format_answers(&answers), "24000\n##..\\n..##\n");
+        assert_eq!(parse_answers(&format_answers(&answers)), answers);
+
+        // a literal backslash-n is not a line break
+        let answers = [Some("a\\nb".into()), Some("C:\\\r\nD\\".into())];
+        assert_eq!(format_answers(&answers), "a\\\\nb\nC:\\\\\\r\\nD\\\\\n");
+        assert_eq!(parse_answers(&format_answers(&answers)), answers);
+        assert_eq!(parse_answers("a\\b\\\n"), [Some("a\\b\\".into()), None]);
+
+        for answer in ["", " 42", "42\n"] {
+            let error = write_answer(1, 1, answer).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
 }
@@ -2,47 +2,379 @@
  * This file contains template code.
  * There is no need to edit this file unless you want to change template functionality.
  */
-use aoc2022::{ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
-use std::process::Command;
+use aoc2022::bench::{bench_part, DEFAULT_ITERATIONS, DEFAULT_WARMUP};
+use aoc2022::history::{self, Mode, Timing, DEFAULT_THRESHOLD, HISTORY_FILE};
+use aoc2022::runner::{parse_days, read_input, run_day, verdict, Verdict};
+use aoc2022::{days, read_answers, PartResult, Status, ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
+use std::path::Path;
+use std::process;
+use std::time::Duration;
 
-fn main() {
-    let total: f64 = (1..=25)
-        .map(|day| {
-            let day = format!("{:02}", day);
+struct Args {
+    json: bool,
+    bench: bool,
+    warmup: usize,
+    iterations: usize,
+    days: Vec<u8>,
+    save_baseline: Option<String>,
+    no_history: bool,
+}
+
+struct CompareArgs {
+    baseline: Option<String>,
+    threshold: f64,
+}
+
+enum Command {
+    Run(Args),
+    Compare(CompareArgs),
+    Verify(Vec<u8>),
+}
+
+fn parse_args() -> Result<Command, String> {
+    let mut args = pico_args::Arguments::from_env();
+    let command = match args.subcommand().map_err(|e| e.to_string())?.as_deref() {
+        None => Command::Run(parse_run_args(&mut args)?),
+        Some("compare") => {
+            let baseline = args
+                .opt_value_from_str("--baseline")
+                .map_err(|e| e.to_string())?;
+            let threshold = args
+                .opt_value_from_str("--threshold")
+                .map_err(|e| e.to_string())?;
+            Command::Compare(CompareArgs {
+                baseline,
+                threshold: threshold.unwrap_or(DEFAULT_THRESHOLD),
+            })
+        }
+        Some("verify") => Command::Verify(parse_days_arg(&mut args)?),
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+    };
+
+    // anything left over is a typo, like `--dasy 3`, that would otherwise be ignored
+    let rest = args.finish();
+    if !rest.is_empty() {
+        let rest = rest
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>();
+        return Err(format!("unexpected arguments: {}", rest.join(" ")));
+    }
+    Ok(command)
+}
+
+/// `--days 3,7-10`, or every day.
+fn parse_days_arg(args: &mut pico_args::Arguments) -> Result<Vec<u8>, String> {
+    let days: Option<String> = args
+        .opt_value_from_str("--days")
+        .map_err(|e| e.to_string())?;
+    match days {
+        Some(spec) => parse_days(&spec),
+        None => Ok((1..=25).collect()),
+    }
+}
+
+fn parse_run_args(args: &mut pico_args::Arguments) -> Result<Args, String> {
+    let days = parse_days_arg(args)?;
+    let warmup = args
+        .opt_value_from_str("--warmup")
+        .map_err(|e| e.to_string())?;
+    let iterations = args
+        .opt_value_from_str("--iterations")
+        .map_err(|e| e.to_string())?;
+    let save_baseline = args
+        .opt_value_from_str("--save-baseline")
+        .map_err(|e| e.to_string())?;
+    let args = Args {
+        // `--json` prints the per-part records as JSON lines, e.g. for CI dashboards
+        json: args.contains("--json"),
+        bench: args.contains("--bench"),
+        warmup: warmup.unwrap_or(DEFAULT_WARMUP),
+        iterations: iterations.unwrap_or(DEFAULT_ITERATIONS),
+        days,
+        save_baseline,
+        no_history: args.contains("--no-history"),
+    };
+    if args.iterations == 0 {
+        return Err("--iterations must be at least 1".into());
+    }
+    Ok(args)
+}
+
+fn print_day_header(day: u8) {
+    println!("----------");
+    println!("{}| Day {:02} |{}", ANSI_BOLD, day, ANSI_RESET);
+    println!("----------");
+}
+
+fn print_part_header(part: u8) {
+    println!("🎄 {}Part {}{} 🎄", ANSI_BOLD, part, ANSI_RESET);
+}
+
+/// What a run or benchmark of the selected days produced.
+struct Summary {
+    total: Duration,
+    failed: bool,
+    timings: Vec<Timing>,
+}
 
-            let mut args = vec!["run", "--bin", &day];
-            if cfg!(not(debug_assertions)) {
-                args.push("--release");
+/// Runs every selected day once. A part failed if it panicked or gave a wrong answer.
+fn run(args: &Args) -> Summary {
+    let mut total = Duration::ZERO;
+    let mut failed = false;
+    let mut timings = Vec::new();
+    for &day in &args.days {
+        let results = match run_day(day) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Day {:02}: {}", day, e);
+                continue;
             }
+        };
+        failed |= results
+            .iter()
+            .any(|result| matches!(result.status, Status::Panicked | Status::WrongAnswer));
+        total += results.iter().map(PartResult::elapsed).sum::<Duration>();
+        timings.extend(
+            results
+                .iter()
+                .filter(|result| result.status == Status::Solved)
+                .map(|result| Timing {
+                    day,
+                    part: result.part,
+                    median_ns: result.duration_ns,
+                }),
+        );
 
-            let cmd = Command::new("cargo").args(&args).output().unwrap();
+        if args.json {
+            for result in &results {
+                println!("{}", result.to_json_line());
+            }
+            continue;
+        }
 
-            println!("----------");
-            println!("{}| Day {} |{}", ANSI_BOLD, day, ANSI_RESET);
-            println!("----------");
+        print_day_header(day);
+        if results
+            .iter()
+            .all(|result| result.status == Status::NotImplemented)
+        {
+            println!("Not implemented.");
+            continue;
+        }
+        for result in &results {
+            print_part_header(result.part);
+            println!("{}", result);
+        }
+    }
+    Summary {
+        total,
+        failed,
+        timings,
+    }
+}
 
-            let output = String::from_utf8(cmd.stdout).unwrap();
-            let is_empty = output.is_empty();
+/// Benchmarks every selected day. The total is the sum of the medians, and a part failed
+/// if it panicked or gave a wrong answer.
+fn bench(args: &Args) -> Summary {
+    let mut total = Duration::ZERO;
+    let mut failed = false;
+    let mut timings = Vec::new();
+    for &day in &args.days {
+        let entry = days::find(day);
+        if !args.json {
+            print_day_header(day);
+            if entry.is_none() {
+                println!("Not implemented.");
+            }
+        }
+        let entry = match entry {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let input = match read_input(day) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("Day {:02}: {}", day, e);
+                continue;
+            }
+        };
 
-            println!(
-                "{}",
-                if is_empty {
-                    "Not solved."
-                } else {
-                    output.trim()
-                }
+        let answers = read_answers(day);
+        for (part, solver) in [(1, entry.part_one), (2, entry.part_two)] {
+            let expected = answers[usize::from(part - 1)].as_deref();
+            let stats = bench_part(
+                day,
+                part,
+                solver,
+                &input,
+                expected,
+                args.warmup,
+                args.iterations,
             );
-
-            if is_empty {
-                0_f64
-            } else {
-                aoc2022::parse_exec_time(&output)
+            match &stats {
+                Ok(stats) => {
+                    total += stats.median();
+                    timings.push(Timing {
+                        day,
+                        part,
+                        median_ns: stats.median_ns,
+                    });
+                }
+                Err(result) => {
+                    failed |= matches!(result.status, Status::Panicked | Status::WrongAnswer)
+                }
+            }
+            match (stats, args.json) {
+                (Ok(stats), true) => println!("{}", stats.to_json_line()),
+                (Ok(stats), false) => {
+                    print_part_header(part);
+                    println!("{}", stats);
+                }
+                (Err(result), true) => eprintln!("Day {:02} part {}: {}", day, part, result),
+                (Err(result), false) => {
+                    print_part_header(part);
+                    println!("{}", result);
+                }
             }
-        })
-        .sum();
+        }
+    }
+    Summary {
+        total,
+        failed,
+        timings,
+    }
+}
+
+/// Compares the latest recorded run against a baseline or the previous run. Returns
+/// whether any part regressed or is no longer timed.
+fn compare(args: &CompareArgs) -> Result<bool, String> {
+    let runs = history::load(Path::new(HISTORY_FILE))
+        .map_err(|e| format!("could not read {}: {}", HISTORY_FILE, e))?;
+    let (before, after) = history::select(&runs, args.baseline.as_deref())?;
+    println!("before: {}", before.describe());
+    println!("after:  {}", after.describe());
+
+    let comparison = history::compare(before, after, args.threshold);
+    if comparison.changes.is_empty() && comparison.missing.is_empty() {
+        println!("No parts were timed in both runs.");
+    }
+    for change in &comparison.changes {
+        println!("{}", change);
+    }
+    for (day, part) in &comparison.missing {
+        println!("Day {:02} part {}: panicked, unsolved or wrong", day, part);
+    }
+    let regressions = comparison
+        .changes
+        .iter()
+        .filter(|change| change.regressed)
+        .count();
+    if regressions > 0 {
+        println!(
+            "{}{} part(s) regressed by more than {}%.{}",
+            ANSI_BOLD, regressions, args.threshold, ANSI_RESET
+        );
+    }
+    if !comparison.missing.is_empty() {
+        println!(
+            "{}{} part(s) are no longer timed.{}",
+            ANSI_BOLD,
+            comparison.missing.len(),
+            ANSI_RESET
+        );
+    }
+    Ok(comparison.failed())
+}
 
+/// Checks every implemented day against its recorded answers. Returns whether any part
+/// was incorrect.
+fn verify(days: &[u8]) -> bool {
+    let (mut correct, mut incorrect, mut unknown) = (0, 0, 0);
+    for &day in days.iter().filter(|&&day| days::find(day).is_some()) {
+        let results = match run_day(day) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Day {:02}: {}", day, e);
+                continue;
+            }
+        };
+        for result in &results {
+            let part = format!("Day {:02} part {}", day, result.part);
+            let answer = result.answer.as_deref().unwrap_or("nothing");
+            match verdict(result) {
+                Verdict::Correct => {
+                    correct += 1;
+                    println!("✅ {}: {}", part, answer);
+                }
+                Verdict::Incorrect => {
+                    incorrect += 1;
+                    match (&result.error, &result.expected) {
+                        (Some(error), _) => println!("❌ {}: panicked: {}", part, error),
+                        (None, Some(expected)) => {
+                            println!("❌ {}: expected {}, got {}", part, expected, answer)
+                        }
+                        (None, None) => println!("❌ {}: {}", part, result),
+                    }
+                }
+                Verdict::Unknown => {
+                    unknown += 1;
+                    println!("❔ {}: {} (no recorded answer)", part, answer);
+                }
+            }
+        }
+    }
     println!(
-        "{}Total:{} {}{:.2}ms{}",
-        ANSI_BOLD, ANSI_RESET, ANSI_ITALIC, total, ANSI_RESET
+        "{}{} correct, {} incorrect, {} without a recorded answer.{}",
+        ANSI_BOLD, correct, incorrect, unknown, ANSI_RESET
     );
+    incorrect > 0
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Compare(args)) => match compare(&args) {
+            Ok(regressed) => process::exit(i32::from(regressed)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Ok(Command::Verify(days)) => process::exit(i32::from(verify(&days))),
+        Err(e) => {
+            eprintln!("Failed to process arguments: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let summary = match args.bench {
+        true => bench(&args),
+        false => run(&args),
+    };
+
+    if !args.json {
+        println!(
+            "{}Total:{} {}{:.2}ms{}",
+            ANSI_BOLD,
+            ANSI_RESET,
+            ANSI_ITALIC,
+            summary.total.as_secs_f64() * 1000_f64,
+            ANSI_RESET
+        );
+    }
+
+    if !args.no_history && !summary.timings.is_empty() {
+        let mode = match args.bench {
+            true => Mode::Bench,
+            false => Mode::Run,
+        };
+        let run = history::Run::new(mode, args.save_baseline.clone(), summary.timings);
+        if let Err(e) = history::append(Path::new(HISTORY_FILE), &run) {
+            eprintln!("could not record run in {}: {}", HISTORY_FILE, e);
+        }
+    }
+
+    if summary.failed {
+        process::exit(1);
+    }
 }
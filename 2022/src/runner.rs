@@ -0,0 +1,217 @@
+/*
+ * This file contains template code.
+ * There is no need to edit this file unless you want to change template functionality.
+ */
+use crate::days::{self, Solver};
+use crate::{read_answers, PartResult, Status};
+use std::any::Any;
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::time::Instant;
+
+/// Parses a day selection like `3,7-10` into sorted, de-duplicated days.
+pub fn parse_days(spec: &str) -> Result<Vec<u8>, String> {
+    let parse_day = |text: &str| match text.trim().parse::<u8>() {
+        Ok(day @ 1..=25) => Ok(day),
+        _ => Err(format!("invalid day '{}' (expected 1-25)", text.trim())),
+    };
+    let mut days = Vec::new();
+    for item in spec.split(',').filter(|item| !item.trim().is_empty()) {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_day(first)?, parse_day(last)?);
+                if first > last {
+                    return Err(format!("empty day range '{}'", item.trim()));
+                }
+                days.extend(first..=last);
+            }
+            None => days.push(parse_day(item)?),
+        }
+    }
+    days.sort_unstable();
+    days.dedup();
+    Ok(days)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic payload".to_string(),
+        },
+    }
+}
+
+thread_local! {
+    /// Set while this thread runs a part, whose panic message ends up in its result.
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs, once per process, a panic hook that stays quiet for threads running a part
+/// and hands every other panic to the previous hook. Swapping hooks per part instead
+/// would race with panics on other threads, e.g. parallel tests.
+fn silence_captured_panics() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !CAPTURING.with(Cell::get) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Runs one part, catching a panic so the remaining days still run.
+pub fn run_part(day: u8, part: u8, solver: Solver, input: &str) -> PartResult {
+    silence_captured_panics();
+    CAPTURING.with(|capturing| capturing.set(true));
+    let timer = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| solver(input)));
+    let elapsed = timer.elapsed();
+    CAPTURING.with(|capturing| capturing.set(false));
+
+    match result {
+        Ok(answer) => PartResult::new(day, part, answer, elapsed),
+        Err(payload) => PartResult::panicked(day, part, panic_message(payload), elapsed),
+    }
+}
+
+/// Reads `src/inputs/NN.txt`; the error names the file if it can't be read.
+pub fn read_input(day: u8) -> Result<String, String> {
+    let path = env::current_dir()
+        .unwrap()
+        .join("src")
+        .join("inputs")
+        .join(format!("{:02}.txt", day));
+    fs::read_to_string(&path).map_err(|e| format!("could not read input {}: {}", path.display(), e))
+}
+
+/// Runs both parts of a day against `src/inputs/NN.txt`, checking them against any
+/// recorded answers. Errors if the day is registered but its input is missing.
+pub fn run_day(day: u8) -> Result<Vec<PartResult>, String> {
+    let entry = match days::find(day) {
+        Some(entry) => entry,
+        None => {
+            return Ok(vec![
+                PartResult::not_implemented(day, 1),
+                PartResult::not_implemented(day, 2),
+            ])
+        }
+    };
+
+    let input = read_input(day)?;
+    let [expected_one, expected_two] = read_answers(day);
+    Ok(vec![
+        run_part(day, 1, entry.part_one, &input).check(expected_one.as_deref()),
+        run_part(day, 2, entry.part_two, &input).check(expected_two.as_deref()),
+    ])
+}
+
+/// How a part fared against its recorded answer, for `cargo all -- verify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    /// Wrong, panicked, or gave no answer where one is recorded.
+    Incorrect,
+    /// Nothing recorded to check against.
+    Unknown,
+}
+
+pub fn verdict(result: &PartResult) -> Verdict {
+    match (result.status, &result.expected) {
+        (Status::Panicked | Status::WrongAnswer, _) => Verdict::Incorrect,
+        (Status::Solved, Some(_)) => Verdict::Correct,
+        (Status::Unsolved, Some(_)) => Verdict::Incorrect,
+        _ => Verdict::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("3"), Ok(vec![3]));
+        assert_eq!(parse_days("3,7-10"), Ok(vec![3, 7, 8, 9, 10]));
+        assert_eq!(parse_days("10-11, 2,10"), Ok(vec![2, 10, 11]));
+        assert_eq!(
+            parse_days("0"),
+            Err("invalid day '0' (expected 1-25)".into())
+        );
+        assert_eq!(
+            parse_days("5-x"),
+            Err("invalid day 'x' (expected 1-25)".into())
+        );
+        assert_eq!(parse_days("9-7"), Err("empty day range '9-7'".into()));
+    }
+
+    #[test]
+    fn test_run_part() {
+        let result = run_part(1, 1, |input| Some(input.len().to_string()), "abc");
+        assert_eq!(result.status, Status::Solved);
+        assert_eq!(result.answer.as_deref(), Some("3"));
+
+        let result = run_part(1, 2, |_| None, "abc");
+        assert_eq!(result.status, Status::Unsolved);
+
+        let result = run_part(1, 2, |input| panic!("bad input {}", input), "abc");
+        assert_eq!(result.status, Status::Panicked);
+        assert_eq!(result.error.as_deref(), Some("bad input abc"));
+        let result = run_part(1, 2, |_| panic!("plain"), "");
+        assert_eq!(result.error.as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn test_run_part_in_parallel() {
+        let threads = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let solver: Solver = match i % 2 {
+                        0 => |input| panic!("bad input {}", input),
+                        _ => |input| Some(input.to_string()),
+                    };
+                    let result = run_part(1, 1, solver, "abc");
+                    (i, result, CAPTURING.with(Cell::get))
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            let (i, result, capturing) = thread.join().unwrap();
+            match i % 2 {
+                0 => assert_eq!(result.error.as_deref(), Some("bad input abc")),
+                _ => assert_eq!(result.answer.as_deref(), Some("abc")),
+            }
+            assert!(!capturing);
+        }
+    }
+
+    #[test]
+    fn test_verdict() {
+        let solved = |answer: Option<&str>, expected| {
+            PartResult::new(1, 1, answer.map(String::from), Duration::ZERO).check(expected)
+        };
+        assert_eq!(verdict(&solved(Some("5"), Some("5"))), Verdict::Correct);
+        assert_eq!(verdict(&solved(Some("4"), Some("5"))), Verdict::Incorrect);
+        assert_eq!(verdict(&solved(None, Some("5"))), Verdict::Incorrect);
+        assert_eq!(verdict(&solved(Some("4"), None)), Verdict::Unknown);
+        assert_eq!(verdict(&solved(None, None)), Verdict::Unknown);
+
+        let panicked = PartResult::panicked(1, 1, "boom".into(), Duration::ZERO);
+        assert_eq!(verdict(&panicked), Verdict::Incorrect);
+    }
+
+    #[test]
+    fn test_unregistered_day() {
+        let results = run_day(25).unwrap();
+        assert!(results
+            .iter()
+            .all(|result| result.status == Status::NotImplemented));
+    }
+}
@@ -0,0 +1,99 @@
+//! Loop detection for any `Vm`.
+use super::{Step, Vm};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Outcome<K> {
+    /// The machine halted or asked for input.
+    Stopped(Step),
+    /// About to execute from a state seen before. `first_seen` and `steps` count the
+    /// steps executed before that state was first reached and before now.
+    Repeated {
+        key: K,
+        first_seen: usize,
+        steps: usize,
+    },
+    StepLimit,
+}
+
+/// Runs until the machine stops or comes back to a state it was in before, as told apart
+/// by `key`. On a repeat the machine is left just before executing from that state again.
+///
+/// Keying on `Vm::ip` finds loops in machines whose control flow never depends on
+/// their data (like the handheld console); keying on `Vm::snapshot` finds exact cycles
+/// in any machine that doesn't take input. Outputs are discarded.
+pub fn detect_loop<V, K, F>(vm: &mut V, mut key: F, limit: usize) -> Result<Outcome<K>, V::Error>
+where
+    V: Vm + ?Sized,
+    K: Hash + Eq + Clone,
+    F: FnMut(&V) -> K,
+{
+    let mut seen = HashMap::new();
+    for steps in 0..limit {
+        let key = key(vm);
+        if let Some(first_seen) = seen.get(&key) {
+            return Ok(Outcome::Repeated {
+                first_seen: *first_seen,
+                key,
+                steps,
+            });
+        }
+        seen.insert(key, steps);
+        match vm.step()? {
+            Step::Executed | Step::Output(_) => {}
+            stop => return Ok(Outcome::Stopped(stop)),
+        }
+    }
+    Ok(Outcome::StepLimit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Countdown;
+
+    #[test]
+    fn test_halts() {
+        let mut vm = Countdown::new(3);
+        assert_eq!(
+            detect_loop(&mut vm, |vm| vm.snapshot(), 1000),
+            Ok(Outcome::Stopped(Step::Halted))
+        );
+        assert!(vm.is_halted());
+    }
+
+    #[test]
+    fn test_repeated_state() {
+        let mut vm = Countdown::new(3);
+        vm.program[1] = "inc";
+        // the counter keeps growing, so no exact state repeats
+        assert_eq!(
+            detect_loop(&mut vm, |vm| vm.snapshot(), 1000),
+            Ok(Outcome::StepLimit)
+        );
+
+        let mut vm = Countdown::new(3);
+        vm.program[1] = "inc";
+        assert_eq!(
+            detect_loop(&mut vm, |vm| vm.ip(), 1000),
+            Ok(Outcome::Repeated {
+                key: 0,
+                first_seen: 0,
+                steps: 3
+            })
+        );
+        assert_eq!(vm.counter, 4);
+
+        let mut vm = Countdown::new(3);
+        vm.program[1] = "jnz 0";
+        assert_eq!(
+            detect_loop(&mut vm, |vm| vm.snapshot(), 1000),
+            Ok(Outcome::Repeated {
+                key: (0, 3),
+                first_seen: 0,
+                steps: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! A breakpoint debugger and REPL that works with any `Vm`. Watchpoints, `mem`, `poke`,
+//! `input` and reverse stepping need the matching optional `Vm` methods, and report an
+//! error on machines without them.
+use super::{format_registers, MemoryWrite, Step, Vm};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Steps kept by `record` without a count.
+pub const DEFAULT_HISTORY: usize = 100_000;
+
+/// The most cells `mem` dumps at once.
+pub const MAX_DUMP: usize = 4096;
+
+/// Why the debugger handed control back.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StopReason {
+    Stepped,
+    Breakpoint(usize),
+    Watchpoint {
+        address: usize,
+        old: i64,
+        new: i64,
+    },
+    AwaitingInput,
+    Halted,
+    /// Running backwards, stopped at the step that made this write.
+    LastWrite(MemoryWrite),
+    /// Running backwards, ran out of recorded history.
+    StartOfHistory,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Stepped => write!(f, "stepped"),
+            StopReason::Breakpoint(address) => write!(f, "breakpoint at {}", address),
+            StopReason::Watchpoint { address, old, new } => {
+                write!(f, "watchpoint [{}] changed {} -> {}", address, old, new)
+            }
+            StopReason::AwaitingInput => write!(f, "waiting for input (use `input`)"),
+            StopReason::Halted => write!(f, "program halted"),
+            StopReason::LastWrite(write) => write!(
+                f,
+                "[{}] written here: {} -> {}",
+                write.address, write.old, write.new
+            ),
+            StopReason::StartOfHistory => write!(f, "reached the start of the recorded history"),
+        }
+    }
+}
+
+pub struct Debugger<V: Vm> {
+    pub vm: V,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    outputs: Vec<i64>,
+}
+
+impl<V: Vm> Debugger<V> {
+    pub fn new(vm: V) -> Self {
+        Self {
+            vm,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn add_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.insert(address)
+    }
+
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    /// Outputs produced since the last call.
+    pub fn take_outputs(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    /// Executes exactly one step.
+    pub fn step(&mut self) -> Result<StopReason, V::Error> {
+        let watched = self
+            .watchpoints
+            .iter()
+            .map(|address| (*address, self.vm.peek(*address)))
+            .collect::<BTreeMap<_, _>>();
+
+        match self.vm.step()? {
+            Step::Executed => {}
+            Step::Output(value) => self.outputs.push(value),
+            Step::AwaitingInput => return Ok(StopReason::AwaitingInput),
+            Step::Halted => return Ok(StopReason::Halted),
+        }
+
+        for (address, old) in watched {
+            match (old, self.vm.peek(address)) {
+                (Some(old), Some(new)) if new != old => {
+                    return Ok(StopReason::Watchpoint { address, old, new })
+                }
+                _ => {}
+            }
+        }
+        Ok(StopReason::Stepped)
+    }
+
+    /// Runs until a breakpoint is reached, a watched cell changes, input is needed or the
+    /// program halts. Always executes at least one step, so continuing from a breakpoint
+    /// makes progress.
+    pub fn continue_execution(&mut self) -> Result<StopReason, V::Error> {
+        loop {
+            match self.step()? {
+                StopReason::Stepped => {}
+                reason => return Ok(reason),
+            }
+            let ip = self.vm.ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(StopReason::Breakpoint(ip));
+            }
+        }
+    }
+
+    /// Undoes exactly one step. Outputs it made are not taken back.
+    pub fn reverse_step(&mut self) -> StopReason {
+        match self.vm.reverse_step() {
+            None => StopReason::StartOfHistory,
+            Some(undone) => match undone.write {
+                Some(write) if self.watchpoints.contains(&write.address) => {
+                    StopReason::LastWrite(write)
+                }
+                _ => StopReason::Stepped,
+            },
+        }
+    }
+
+    /// Runs backwards until a breakpoint is reached, a watched cell was written or the
+    /// recorded history runs out.
+    pub fn reverse_continue(&mut self) -> StopReason {
+        loop {
+            match self.reverse_step() {
+                StopReason::Stepped => {}
+                reason => return reason,
+            }
+            let ip = self.vm.ip();
+            if self.breakpoints.contains(&ip) {
+                return StopReason::Breakpoint(ip);
+            }
+        }
+    }
+
+    /// Runs backwards to the step that last wrote `address`, leaving it as the next one
+    /// to execute.
+    pub fn reverse_to_write(&mut self, address: usize) -> StopReason {
+        loop {
+            match self.vm.reverse_step() {
+                None => return StopReason::StartOfHistory,
+                Some(undone) => match undone.write {
+                    Some(write) if write.address == address => return StopReason::LastWrite(write),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    /// Runs a single REPL command, returning the text to show the user. Parse errors and
+    /// machine errors both come back as messages.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let out = match name {
+            "break" | "b" => {
+                let address = number(words.next(), None, "address")?;
+                self.add_breakpoint(address);
+                format!("breakpoint set at {}", address)
+            }
+            "delete" | "d" => {
+                let address = number(words.next(), None, "address")?;
+                match self.remove_breakpoint(address) {
+                    true => format!("breakpoint at {} removed", address),
+                    false => format!("no breakpoint at {}", address),
+                }
+            }
+            "watch" | "w" => {
+                let address = number(words.next(), None, "address")?;
+                if self.vm.peek(address).is_none() {
+                    return Err(format!("no memory cell at {}", address));
+                }
+                self.add_watchpoint(address);
+                format!("watching [{}]", address)
+            }
+            "unwatch" => {
+                let address = number(words.next(), None, "address")?;
+                match self.remove_watchpoint(address) {
+                    true => format!("no longer watching [{}]", address),
+                    false => format!("not watching [{}]", address),
+                }
+            }
+            "step" | "s" => {
+                let mut reason = StopReason::Stepped;
+                for _ in 0..number(words.next(), Some(1), "count")? {
+                    reason = self.step().map_err(|e| e.to_string())?;
+                    if reason != StopReason::Stepped {
+                        break;
+                    }
+                }
+                self.report(reason)
+            }
+            "continue" | "c" => {
+                let reason = self.continue_execution().map_err(|e| e.to_string())?;
+                self.report(reason)
+            }
+            "record" => {
+                let capacity = number(words.next(), Some(DEFAULT_HISTORY), "count")?;
+                self.vm.start_recording(capacity)?;
+                format!("recording the last {} steps", capacity)
+            }
+            "reverse-step" | "rs" => {
+                let mut reason = StopReason::Stepped;
+                for _ in 0..number(words.next(), Some(1), "count")? {
+                    reason = self.reverse_step();
+                    if reason != StopReason::Stepped {
+                        break;
+                    }
+                }
+                self.report(reason)
+            }
+            "reverse-continue" | "rc" => {
+                let reason = match words.next() {
+                    Some(word) => self.reverse_to_write(number(Some(word), None, "address")?),
+                    None => self.reverse_continue(),
+                };
+                self.report(reason)
+            }
+            "mem" | "m" => {
+                let range = parse_range(words.next())?;
+                let cells = range
+                    .clone()
+                    .map(|address| {
+                        self.vm
+                            .peek(address)
+                            .map(|value| value.to_string())
+                            .ok_or_else(|| format!("no memory cell at {}", address))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let rows = cells
+                    .chunks(8)
+                    .enumerate()
+                    .map(|(i, row)| format!("{:>6}: {}", range.start + i * 8, row.join(" ")));
+                rows.collect::<Vec<_>>().join("\n")
+            }
+            "poke" => {
+                let address = number(words.next(), None, "address")?;
+                let value: i64 = number(words.next(), None, "value")?;
+                self.vm.poke(address, value)?;
+                format!("[{}] = {}", address, value)
+            }
+            "regs" | "r" => self.registers(),
+            "input" | "i" => {
+                let values = words
+                    .flat_map(|word| word.split(','))
+                    .filter(|value| !value.is_empty())
+                    .map(|value| number(Some(value), None, "value"))
+                    .collect::<Result<Vec<i64>, _>>()?;
+                for value in values {
+                    self.vm.push_input(value)?;
+                }
+                let queued = self.vm.pending_inputs().map_or(0, |inputs| inputs.len());
+                format!("{} input(s) queued", queued)
+            }
+            "list" | "l" => {
+                let mut lines = Vec::new();
+                let mut address = self.vm.ip();
+                for _ in 0..number(words.next(), Some(5), "count")? {
+                    match self.vm.instruction(address) {
+                        Some((text, len)) => {
+                            lines.push(format!("{:>6}  {}", address, text));
+                            address += len.max(1);
+                        }
+                        None => break,
+                    }
+                }
+                lines.join("\n")
+            }
+            "help" | "h" | "?" => HELP.to_string(),
+            _ => return Err(format!("unknown command '{}' (try `help`)", name)),
+        };
+        Ok(out)
+    }
+
+    /// The registers, then the input queue and recorded history if the machine has them.
+    fn registers(&self) -> String {
+        let mut out = format_registers(&self.vm);
+        if let Some(inputs) = self.vm.pending_inputs() {
+            out.push_str(&format!(" inputs={:?}", inputs));
+        }
+        if let Some(steps) = self.vm.recorded_steps() {
+            out.push_str(&format!(" history={}", steps));
+        }
+        out
+    }
+
+    fn report(&mut self, reason: StopReason) -> String {
+        let mut out = String::new();
+        for value in self.take_outputs() {
+            out.push_str(&format!("output: {}\n", value));
+        }
+        if reason != StopReason::Stepped {
+            out.push_str(&format!("{}\n", reason));
+        }
+        let ip = self.vm.ip();
+        match self.vm.instruction(ip) {
+            Some((text, _)) => out.push_str(&format!("{:>6}  {}", ip, text)),
+            None => out.push_str(&format!("{:>6}  (end of program)", ip)),
+        }
+        out
+    }
+}
+
+/// Parses a command argument, falling back to `default` when it was left out.
+fn number<T: FromStr>(word: Option<&str>, default: Option<T>, name: &str) -> Result<T, String> {
+    match (word, default) {
+        (Some(word), _) => word
+            .parse()
+            .map_err(|_| format!("invalid number '{}'", word)),
+        (None, Some(default)) => Ok(default),
+        (None, None) => Err(format!("missing {}", name)),
+    }
+}
+
+/// `a` or `a..b`, at most `MAX_DUMP` cells long.
+fn parse_range(arg: Option<&str>) -> Result<Range<usize>, String> {
+    let arg = arg.ok_or("missing address")?;
+    match arg.find("..") {
+        Some(i) => {
+            let start: usize = number(Some(&arg[..i]), None, "address")?;
+            let end: usize = number(Some(&arg[i + 2..]), None, "address")?;
+            match end.checked_sub(start) {
+                Some(len) if len > MAX_DUMP => Err(format!(
+                    "range '{}' is {} cells, at most {} can be shown",
+                    arg, len, MAX_DUMP
+                )),
+                Some(_) => Ok(start..end),
+                None => Err(format!("empty range '{}'", arg)),
+            }
+        }
+        None => {
+            let start: usize = number(Some(arg), None, "address")?;
+            start
+                .checked_add(1)
+                .map(|end| start..end)
+                .ok_or_else(|| format!("invalid number '{}'", arg))
+        }
+    }
+}
+
+pub const HELP: &str = "\
+commands:
+  break <addr>        set a breakpoint          (b)
+  delete <addr>       remove a breakpoint       (d)
+  watch <addr>        stop when a cell changes  (w)
+  unwatch <addr>      remove a watchpoint
+  step [n]            execute n steps           (s)
+  continue            run to the next stop      (c)
+  record [n]          keep history for the last n steps
+  reverse-step [n]    undo n steps              (rs)
+  reverse-continue [addr]
+                      run back to the previous stop, or to
+                      the last write of addr    (rc)
+  mem <a>[..<b>]      dump up to 4096 cells     (m)
+  poke <addr> <val>   write memory
+  regs                show ip and registers     (r)
+  input <v>[,<v>..]   queue input values        (i)
+  list [n]            list from ip              (l)
+  help / quit";
+
+/// Reads commands from `input` until it ends or says `quit`.
+pub fn repl<V, R, W>(debugger: &mut Debugger<V>, input: R, mut output: W) -> io::Result<()>
+where
+    V: Vm,
+    R: BufRead,
+    W: Write,
+{
+    write!(output, "(vmdb) ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "" => {}
+            "quit" | "q" | "exit" => break,
+            command => match debugger.execute(command) {
+                Ok(text) => writeln!(output, "{}", text)?,
+                Err(e) => writeln!(output, "error: {}", e)?,
+            },
+        }
+        write!(output, "(vmdb) ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Countdown;
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new(Countdown::new(2));
+        debugger.add_breakpoint(2);
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Breakpoint(2)));
+        assert_eq!(debugger.take_outputs(), [2]);
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Breakpoint(2)));
+        assert!(debugger.remove_breakpoint(2));
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Halted));
+        assert_eq!(debugger.take_outputs(), [1]);
+    }
+
+    #[test]
+    fn test_watchpoints_and_reverse() {
+        let mut debugger = Debugger::new(Countdown::new(2));
+        debugger.vm.start_recording(DEFAULT_HISTORY).unwrap();
+        debugger.add_watchpoint(0);
+        assert_eq!(
+            debugger.continue_execution(),
+            Ok(StopReason::Watchpoint {
+                address: 0,
+                old: 2,
+                new: 1
+            })
+        );
+        assert_eq!(debugger.vm.ip, 2);
+        assert!(debugger.remove_watchpoint(0));
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Halted));
+        assert_eq!(debugger.take_outputs(), [2, 1]);
+
+        // back to where the counter hit zero
+        assert_eq!(
+            debugger.reverse_to_write(0),
+            StopReason::LastWrite(MemoryWrite {
+                address: 0,
+                old: 1,
+                new: 0
+            })
+        );
+        assert_eq!((debugger.vm.ip, debugger.vm.counter), (1, 1));
+
+        debugger.add_breakpoint(0);
+        assert_eq!(debugger.reverse_continue(), StopReason::Breakpoint(0));
+        debugger.add_watchpoint(0);
+        assert_eq!(
+            debugger.reverse_continue(),
+            StopReason::LastWrite(MemoryWrite {
+                address: 0,
+                old: 2,
+                new: 1
+            })
+        );
+        assert!(debugger.remove_breakpoint(0));
+        assert_eq!(debugger.reverse_continue(), StopReason::StartOfHistory);
+        assert_eq!((debugger.vm.ip, debugger.vm.counter), (0, 2));
+
+        // and forwards again, replaying the same run
+        assert!(debugger.remove_watchpoint(0));
+        assert_eq!(debugger.continue_execution(), Ok(StopReason::Halted));
+        assert_eq!(debugger.take_outputs(), [2, 1]);
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut debugger = Debugger::new(Countdown::new(1));
+        assert_eq!(
+            debugger.execute("step"),
+            Ok("output: 1\n     1  dec".into())
+        );
+        assert_eq!(
+            debugger.execute("regs"),
+            Ok("ip=1 counter=1 inputs=[]".into())
+        );
+        assert_eq!(
+            debugger.execute("list 9"),
+            Ok("     1  dec\n     2  jnz 0\n     3  hlt".into())
+        );
+        assert_eq!(
+            debugger.execute("c"),
+            Ok("program halted\n     3  hlt".into())
+        );
+        assert_eq!(
+            debugger.execute("break x"),
+            Err("invalid number 'x'".into())
+        );
+        assert_eq!(debugger.execute("delete"), Err("missing address".into()));
+
+        debugger.vm.program[3] = "nop";
+        assert_eq!(debugger.execute("s"), Err("bad instruction nop".into()));
+    }
+
+    #[test]
+    fn test_execute_memory_and_reverse() {
+        let mut debugger = Debugger::new(Countdown::new(0));
+        debugger.vm.program.insert(0, "in");
+        let mut run = |line: &str| debugger.execute(line);
+
+        assert_eq!(run("record 10"), Ok("recording the last 10 steps".into()));
+        assert_eq!(
+            run("c"),
+            Ok("waiting for input (use `input`)\n     0  in".into())
+        );
+        assert_eq!(run("input 1"), Ok("1 input(s) queued".into()));
+        assert_eq!(run("w 0"), Ok("watching [0]".into()));
+        assert_eq!(
+            run("c"),
+            Ok("watchpoint [0] changed 0 -> 1\n     1  out".into())
+        );
+        assert_eq!(run("regs"), Ok("ip=1 counter=1 inputs=[] history=1".into()));
+        assert_eq!(run("mem 0"), Ok("     0: 1".into()));
+        assert_eq!(run("mem 0..2"), Err("no memory cell at 1".into()));
+        assert_eq!(
+            run("mem 0..100000000"),
+            Err("range '0..100000000' is 100000000 cells, at most 4096 can be shown".into())
+        );
+        assert_eq!(run("mem 2..1"), Err("empty range '2..1'".into()));
+        assert_eq!(run("poke 0 1"), Ok("[0] = 1".into()));
+        assert_eq!(run("poke 1 0"), Err("no cell at 1".into()));
+        assert_eq!(run("poke 0"), Err("missing value".into()));
+        assert_eq!(run("watch 1"), Err("no memory cell at 1".into()));
+
+        assert_eq!(run("unwatch 0"), Ok("no longer watching [0]".into()));
+        assert_eq!(
+            run("c"),
+            Ok("output: 1\nprogram halted\n     4  hlt".into())
+        );
+        assert_eq!(
+            run("rc 0"),
+            Ok("[0] written here: 1 -> 0\n     2  dec".into())
+        );
+        assert_eq!(
+            run("rs 9"),
+            Ok("reached the start of the recorded history\n     0  in".into())
+        );
+        assert_eq!(
+            run("regs"),
+            Ok("ip=0 counter=0 inputs=[1] history=0".into())
+        );
+    }
+
+    #[test]
+    fn test_repl() {
+        let mut debugger = Debugger::new(Countdown::new(1));
+        let mut output = Vec::new();
+        repl(&mut debugger, &b"b 2\nc\nfoo\nquit\ns\n"[..], &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "(vmdb) breakpoint set at 2\n\
+             (vmdb) output: 1\nbreakpoint at 2\n     2  jnz 0\n\
+             (vmdb) error: unknown command 'foo' (try `help`)\n\
+             (vmdb) "
+        );
+    }
+}
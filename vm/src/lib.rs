@@ -0,0 +1,328 @@
+//! A common interface for the puzzle virtual machines (the 2019 Intcode computer, the
+//! 2020 handheld console and the 2022 CRT's CPU), so tooling is written once: tracing,
+//! loop detection, snapshots and a breakpoint debugger.
+//!
+//! Only the core methods are required. Machines with addressable data memory, an input
+//! queue or an undo history opt into the debugger's watchpoints, `mem`, `input` and
+//! reverse stepping by overriding the matching defaults.
+use std::fmt;
+
+pub mod cycle;
+pub mod debugger;
+pub mod trace;
+
+/// What a single `Vm::step` did.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Step {
+    Executed,
+    Output(i64),
+    /// Nothing was executed; the machine needs input first.
+    AwaitingInput,
+    /// Nothing was executed; the machine has stopped.
+    Halted,
+}
+
+/// A memory cell written by a single step.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MemoryWrite {
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+/// A step that `Vm::reverse_step` undid.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Undone {
+    /// Where the undone step ran, which is now the instruction pointer again.
+    pub ip: usize,
+    pub write: Option<MemoryWrite>,
+}
+
+pub trait Vm {
+    type Error: fmt::Display;
+    type Snapshot: Clone;
+
+    /// Executes one instruction, or one clock tick for machines that count cycles.
+    fn step(&mut self) -> Result<Step, Self::Error>;
+
+    fn ip(&self) -> usize;
+
+    fn is_halted(&self) -> bool;
+
+    /// Registers other than the instruction pointer, e.g. `[("acc", 5)]`.
+    fn registers(&self) -> Vec<(&'static str, i64)>;
+
+    /// How many addresses the program occupies.
+    fn memory_len(&self) -> usize;
+
+    /// The instruction at `address` in the machine's own assembly syntax, along with
+    /// how many addresses it takes up. `None` past the end of memory.
+    fn instruction(&self, address: usize) -> Option<(String, usize)>;
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    fn restore(&mut self, snapshot: &Self::Snapshot);
+
+    /// The data memory cell at `address`, or `None` if there isn't one.
+    fn peek(&self, _address: usize) -> Option<i64> {
+        None
+    }
+
+    fn poke(&mut self, _address: usize, _value: i64) -> Result<(), String> {
+        Err("this machine has no writable memory".into())
+    }
+
+    /// Queues a value for the next instruction that reads input.
+    fn push_input(&mut self, _value: i64) -> Result<(), String> {
+        Err("this machine takes no input".into())
+    }
+
+    /// Queued inputs not yet consumed, or `None` for machines that take no input.
+    fn pending_inputs(&self) -> Option<Vec<i64>> {
+        None
+    }
+
+    /// Keeps the last `capacity` steps from now on so they can be undone.
+    fn start_recording(&mut self, _capacity: usize) -> Result<(), String> {
+        Err("this machine can't run backwards".into())
+    }
+
+    /// How many steps can currently be undone, or `None` when not recording.
+    fn recorded_steps(&self) -> Option<usize> {
+        None
+    }
+
+    /// Undoes the most recent step, or returns `None` once the recorded history is used up.
+    fn reverse_step(&mut self) -> Option<Undone> {
+        None
+    }
+
+    /// While enabled, the machine keeps track of what each step read and wrote, for
+    /// `last_reads` and `last_write`.
+    fn set_tracing(&mut self, _enabled: bool) {}
+
+    /// The values the last step read, in order.
+    fn last_reads(&self) -> Vec<i64> {
+        Vec::new()
+    }
+
+    fn last_write(&self) -> Option<MemoryWrite> {
+        None
+    }
+}
+
+/// `ip=3 acc=5`, for status lines.
+pub fn format_registers<V: Vm + ?Sized>(vm: &V) -> String {
+    let mut out = format!("ip={}", vm.ip());
+    for (name, value) in vm.registers() {
+        out.push_str(&format!(" {}={}", name, value));
+    }
+    out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Counts a register down to zero, outputting it on the way, then halts:
+    /// `0: out`, `1: dec`, `2: jnz 0`, `3: hlt`. An `inc` in place of the `dec`
+    /// loops forever, and `in` loads the counter from the input queue.
+    ///
+    /// The counter doubles as data memory cell 0, so the optional `Vm` methods have
+    /// something to work on.
+    #[derive(Debug, Clone)]
+    pub(crate) struct Countdown {
+        pub(crate) program: Vec<&'static str>,
+        pub(crate) ip: usize,
+        pub(crate) counter: i64,
+        inputs: VecDeque<i64>,
+        /// Steps recorded so far, as `(ip, counter, input)`, and how many to keep.
+        history: Option<VecDeque<(usize, i64, Option<i64>)>>,
+        capacity: usize,
+        /// What the last step read and wrote, while tracing.
+        traced: Option<(Vec<i64>, Option<MemoryWrite>)>,
+    }
+
+    impl Countdown {
+        pub(crate) fn new(counter: i64) -> Self {
+            Countdown {
+                program: vec!["out", "dec", "jnz 0", "hlt"],
+                ip: 0,
+                counter,
+                inputs: VecDeque::new(),
+                history: None,
+                capacity: 0,
+                traced: None,
+            }
+        }
+
+        fn execute(&mut self) -> Result<(Step, Option<i64>, Vec<i64>), String> {
+            let mut step = Step::Executed;
+            let (mut input, mut reads) = (None, Vec::new());
+            match *self.program.get(self.ip).ok_or("ip out of range")? {
+                "out" => {
+                    reads.push(self.counter);
+                    step = Step::Output(self.counter);
+                }
+                "dec" => {
+                    reads.push(self.counter);
+                    self.counter -= 1;
+                }
+                "inc" => {
+                    reads.push(self.counter);
+                    self.counter += 1;
+                }
+                "in" => match self.inputs.pop_front() {
+                    Some(value) => {
+                        input = Some(value);
+                        self.counter = value;
+                    }
+                    None => return Ok((Step::AwaitingInput, None, reads)),
+                },
+                "jnz 0" if self.counter != 0 => {
+                    self.ip = 0;
+                    return Ok((step, input, vec![self.counter]));
+                }
+                "jnz 0" => reads.push(self.counter),
+                "hlt" => return Ok((Step::Halted, None, reads)),
+                other => return Err(format!("bad instruction {}", other)),
+            }
+            self.ip += 1;
+            Ok((step, input, reads))
+        }
+    }
+
+    impl Vm for Countdown {
+        type Error = String;
+        type Snapshot = (usize, i64);
+
+        fn step(&mut self) -> Result<Step, String> {
+            let (ip, counter) = (self.ip, self.counter);
+            let (step, input, reads) = self.execute()?;
+            if let Step::AwaitingInput | Step::Halted = step {
+                return Ok(step);
+            }
+            if let Some(steps) = self.history.as_mut() {
+                if steps.len() == self.capacity {
+                    steps.pop_front();
+                }
+                steps.push_back((ip, counter, input));
+            }
+            let write = Some(MemoryWrite {
+                address: 0,
+                old: counter,
+                new: self.counter,
+            })
+            .filter(|_| self.counter != counter);
+            if let Some(traced) = self.traced.as_mut() {
+                *traced = (reads, write);
+            }
+            Ok(step)
+        }
+
+        fn ip(&self) -> usize {
+            self.ip
+        }
+
+        fn is_halted(&self) -> bool {
+            self.program.get(self.ip) == Some(&"hlt")
+        }
+
+        fn registers(&self) -> Vec<(&'static str, i64)> {
+            vec![("counter", self.counter)]
+        }
+
+        fn memory_len(&self) -> usize {
+            self.program.len()
+        }
+
+        fn instruction(&self, address: usize) -> Option<(String, usize)> {
+            self.program
+                .get(address)
+                .map(|instruction| (instruction.to_string(), 1))
+        }
+
+        fn snapshot(&self) -> (usize, i64) {
+            (self.ip, self.counter)
+        }
+
+        fn restore(&mut self, snapshot: &(usize, i64)) {
+            self.ip = snapshot.0;
+            self.counter = snapshot.1;
+        }
+
+        fn peek(&self, address: usize) -> Option<i64> {
+            Some(self.counter).filter(|_| address == 0)
+        }
+
+        fn poke(&mut self, address: usize, value: i64) -> Result<(), String> {
+            if address != 0 {
+                return Err(format!("no cell at {}", address));
+            }
+            self.counter = value;
+            Ok(())
+        }
+
+        fn push_input(&mut self, value: i64) -> Result<(), String> {
+            self.inputs.push_back(value);
+            Ok(())
+        }
+
+        fn pending_inputs(&self) -> Option<Vec<i64>> {
+            Some(self.inputs.iter().copied().collect())
+        }
+
+        fn start_recording(&mut self, capacity: usize) -> Result<(), String> {
+            self.history = Some(VecDeque::new());
+            self.capacity = capacity;
+            Ok(())
+        }
+
+        fn recorded_steps(&self) -> Option<usize> {
+            self.history.as_ref().map(VecDeque::len)
+        }
+
+        fn reverse_step(&mut self) -> Option<Undone> {
+            let (ip, counter, input) = self.history.as_mut()?.pop_back()?;
+            let write = Some(MemoryWrite {
+                address: 0,
+                old: counter,
+                new: self.counter,
+            })
+            .filter(|_| self.counter != counter);
+            if let Some(value) = input {
+                self.inputs.push_front(value);
+            }
+            self.ip = ip;
+            self.counter = counter;
+            Some(Undone { ip, write })
+        }
+
+        fn set_tracing(&mut self, enabled: bool) {
+            self.traced = Some((Vec::new(), None)).filter(|_| enabled);
+        }
+
+        fn last_reads(&self) -> Vec<i64> {
+            self.traced
+                .as_ref()
+                .map_or_else(Vec::new, |(reads, _)| reads.clone())
+        }
+
+        fn last_write(&self) -> Option<MemoryWrite> {
+            self.traced.as_ref().and_then(|(_, write)| *write)
+        }
+    }
+
+    #[test]
+    fn test_format_registers() {
+        let mut vm = Countdown::new(2);
+        assert_eq!(format_registers(&vm), "ip=0 counter=2");
+        let snapshot = vm.snapshot();
+        vm.step().unwrap();
+        vm.step().unwrap();
+        assert_eq!(format_registers(&vm), "ip=2 counter=1");
+        vm.restore(&snapshot);
+        assert_eq!(format_registers(&vm), "ip=0 counter=2");
+    }
+}
@@ -0,0 +1,185 @@
+//! Step-by-step execution traces and profiles for any `Vm`.
+use super::{MemoryWrite, Step, Vm};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// One executed step: where it ran, what it was, what it did and the registers after.
+/// `reads` and `write` stay empty for machines that don't track them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceLine {
+    pub ip: usize,
+    pub instruction: String,
+    pub step: Step,
+    pub registers: Vec<(&'static str, i64)>,
+    /// The values the step read, in order.
+    pub reads: Vec<i64>,
+    pub write: Option<MemoryWrite>,
+}
+
+impl fmt::Display for TraceLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>6}  {:<24}", self.ip, self.instruction)?;
+        for (name, value) in &self.registers {
+            write!(f, " {}={}", name, value)?;
+        }
+        if !self.reads.is_empty() {
+            let reads = self.reads.iter().map(i64::to_string).collect::<Vec<_>>();
+            write!(f, "  reads {}", reads.join(", "))?;
+        }
+        if let Some(write) = self.write {
+            write!(f, "  [{}] {} => {}", write.address, write.old, write.new)?;
+        }
+        if let Step::Output(value) = self.step {
+            write!(f, "  -> {}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs up to `limit` steps, handing each executed one to `sink`. Returns the step that
+/// stopped the run, which is the last one executed if the limit was reached.
+pub fn trace<V, F>(vm: &mut V, limit: usize, sink: F) -> Result<Step, V::Error>
+where
+    V: Vm + ?Sized,
+    F: FnMut(&TraceLine),
+{
+    vm.set_tracing(true);
+    let stop = trace_steps(vm, limit, sink);
+    vm.set_tracing(false);
+    stop
+}
+
+fn trace_steps<V, F>(vm: &mut V, limit: usize, mut sink: F) -> Result<Step, V::Error>
+where
+    V: Vm + ?Sized,
+    F: FnMut(&TraceLine),
+{
+    let mut last = Step::Executed;
+    for _ in 0..limit {
+        let ip = vm.ip();
+        let instruction = vm
+            .instruction(ip)
+            .map_or_else(|| "??".to_string(), |(text, _)| text);
+        last = vm.step()?;
+        if let Step::AwaitingInput | Step::Halted = last {
+            break;
+        }
+        sink(&TraceLine {
+            ip,
+            instruction,
+            step: last,
+            registers: vm.registers(),
+            reads: vm.last_reads(),
+            write: vm.last_write(),
+        });
+    }
+    Ok(last)
+}
+
+/// Counts traced steps per mnemonic (the instruction's first word) and per address.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profiler {
+    pub cycles: u64,
+    pub opcode_counts: BTreeMap<String, u64>,
+    pub address_counts: HashMap<usize, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, line: &TraceLine) {
+        let mnemonic = line.instruction.split_whitespace().next().unwrap_or("??");
+        self.cycles += 1;
+        *self.opcode_counts.entry(mnemonic.to_string()).or_insert(0) += 1;
+        *self.address_counts.entry(line.ip).or_insert(0) += 1;
+    }
+
+    /// The `count` most executed instruction addresses, busiest first.
+    pub fn hot_addresses(&self, count: usize) -> Vec<(usize, u64)> {
+        let mut addresses = self
+            .address_counts
+            .iter()
+            .map(|(address, hits)| (*address, *hits))
+            .collect::<Vec<_>>();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses.truncate(count);
+        addresses
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |count: u64| count as f64 * 100.0 / self.cycles.max(1) as f64;
+        writeln!(f, "total cycles: {}", self.cycles)?;
+        writeln!(f, "by opcode:")?;
+        let mut opcodes = self.opcode_counts.iter().collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+        for (mnemonic, count) in opcodes {
+            writeln!(
+                f,
+                "  {:<4} {:>12} {:>6.2}%",
+                mnemonic,
+                count,
+                percent(*count)
+            )?;
+        }
+        writeln!(f, "hot addresses:")?;
+        for (address, count) in self.hot_addresses(10) {
+            writeln!(f, "  {:>6} {:>10} {:>6.2}%", address, count, percent(count))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Countdown;
+
+    #[test]
+    fn test_trace() {
+        let mut lines = Vec::new();
+        let mut vm = Countdown::new(1);
+        let stop = trace(&mut vm, 100, |line| lines.push(line.to_string()));
+        assert_eq!(stop, Ok(Step::Halted));
+        assert_eq!(
+            lines,
+            [
+                "     0  out                      counter=1  reads 1  -> 1",
+                "     1  dec                      counter=0  reads 1  [0] 1 => 0",
+                "     2  jnz 0                    counter=0  reads 0",
+            ]
+        );
+        // tracing is only switched on for the run
+        vm.step().unwrap();
+        assert_eq!(vm.last_reads(), []);
+    }
+
+    #[test]
+    fn test_profiler() {
+        let mut profiler = Profiler::new();
+        let mut vm = Countdown::new(3);
+        trace(&mut vm, 100, |line| profiler.record(line)).unwrap();
+        // 3 full loops of out/dec/jnz
+        assert_eq!(profiler.cycles, 9);
+        assert_eq!(profiler.opcode_counts["jnz"], 3);
+        assert_eq!(profiler.hot_addresses(2), [(0, 3), (1, 3)]);
+        assert!(profiler.to_string().starts_with("total cycles: 9\n"));
+    }
+
+    #[test]
+    fn test_limit_and_errors() {
+        let mut vm = Countdown::new(5);
+        let mut count = 0;
+        assert_eq!(trace(&mut vm, 4, |_| count += 1), Ok(Step::Output(4)));
+        assert_eq!(count, 4);
+
+        vm.program[1] = "nop";
+        assert_eq!(
+            trace(&mut vm, 4, |_| {}),
+            Err("bad instruction nop".to_string())
+        );
+    }
+}